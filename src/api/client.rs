@@ -0,0 +1,334 @@
+use iced::futures::{SinkExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AiMessage, AiMessageFrom,
+    api::{
+        error::AiError,
+        providers::get_chat_stream,
+        retry::{RetryPolicy, send_request},
+        sse::SseParser,
+    },
+    config::AppConfig,
+};
+
+#[derive(Debug, Serialize)]
+struct ChatRequest {
+    model: String,
+    messages: Vec<ChatMessage>,
+    stream: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChatMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatStreamChunk {
+    choices: Vec<ChatStreamChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatStreamChoice {
+    delta: ChatDelta,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatDelta {
+    content: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+fn get_role(from: &AiMessageFrom) -> &'static str {
+    match from {
+        AiMessageFrom::User => "user",
+        AiMessageFrom::System => "system",
+        AiMessageFrom::Assistant => "assistant",
+    }
+}
+
+// Client for any server speaking the OpenAI chat completions protocol
+#[derive(Debug, Clone)]
+pub struct ChatClient {
+    pub base_url: String,
+    pub api_key: String,
+    pub model: String,
+    pub retry: RetryPolicy,
+}
+
+impl ChatClient {
+    // Use the configured url, or the provider default when the user left it empty
+    pub fn from_config(config: &AppConfig, default_url: &str) -> Self {
+        let base_url = if config.ai_url.is_empty() {
+            default_url
+        } else {
+            config.ai_url.as_str()
+        };
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone(),
+            model: config.ai_model.clone(),
+            retry: RetryPolicy::from_config(config),
+        }
+    }
+
+    fn get_url(&self, path: &str) -> Result<String, AiError> {
+        if self.base_url.is_empty() {
+            return Err(AiError::Config(
+                "No AI url configured, set ai_url in settings".to_string(),
+            ));
+        }
+        Ok(format!("{}{}", self.base_url, path))
+    }
+
+    fn with_auth(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if self.api_key.is_empty() {
+            request
+        } else {
+            request.bearer_auth(&self.api_key)
+        }
+    }
+
+    fn build_chat_request(
+        &self,
+        messages: &[AiMessage],
+        stream: bool,
+    ) -> Result<reqwest::RequestBuilder, AiError> {
+        let body = ChatRequest {
+            model: self.model.clone(),
+            messages: messages
+                .iter()
+                .map(|m| ChatMessage {
+                    role: get_role(&m.from).to_string(),
+                    content: m.content.clone(),
+                })
+                .collect(),
+            stream,
+        };
+        let url = self.get_url("/chat/completions")?;
+        Ok(self.with_auth(reqwest::Client::new().post(url).json(&body)))
+    }
+
+    // Send the whole conversation and return the reply
+    pub async fn send_chat(self, messages: Vec<AiMessage>) -> Result<String, AiError> {
+        let response =
+            send_request(self.build_chat_request(&messages, false)?, &self.retry).await?;
+
+        let chat_response = response
+            .json::<ChatResponse>()
+            .await
+            .map_err(|e| AiError::MalformedResponse(e.to_string()))?;
+
+        chat_response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.content)
+            .ok_or_else(|| AiError::MalformedResponse("Response contains no choice".to_string()))
+    }
+
+    // Same as send_chat but yields the reply piece by piece as the server generates it
+    pub fn stream_chat(
+        self,
+        messages: Vec<AiMessage>,
+    ) -> impl Stream<Item = Result<String, AiError>> {
+        get_chat_stream(move |mut output| async move {
+            let response =
+                send_request(self.build_chat_request(&messages, true)?, &self.retry).await?;
+            let mut bytes = response.bytes_stream();
+            let mut parser = SseParser::new();
+
+            while let Some(chunk) = bytes.next().await {
+                let chunk =
+                    chunk.map_err(|e| AiError::Network(format!("Stream interrupted: {}", e)))?;
+                for event in parser.push(&chunk) {
+                    if event.data == "[DONE]" {
+                        return Ok(());
+                    }
+                    if let Some(delta) = parse_delta(&event.data)?
+                        && output.send(Ok(delta)).await.is_err()
+                    {
+                        // Nobody listens anymore, no need to keep reading
+                        return Ok(());
+                    }
+                }
+            }
+
+            if let Some(event) = parser.finish()
+                && event.data != "[DONE]"
+                && let Some(delta) = parse_delta(&event.data)?
+            {
+                let _ = output.send(Ok(delta)).await;
+            }
+            Ok(())
+        })
+    }
+
+    pub async fn list_models(self) -> Result<Vec<String>, AiError> {
+        let url = self.get_url("/models")?;
+        let response =
+            send_request(self.with_auth(reqwest::Client::new().get(url)), &self.retry).await?;
+
+        let models = response
+            .json::<ModelList>()
+            .await
+            .map_err(|e| AiError::MalformedResponse(e.to_string()))?;
+        Ok(models.data.into_iter().map(|m| m.id).collect())
+    }
+}
+
+fn parse_delta(data: &str) -> Result<Option<String>, AiError> {
+    let chunk = serde_json::from_str::<ChatStreamChunk>(data)
+        .map_err(|e| AiError::MalformedResponse(format!("Invalid stream chunk: {}", e)))?;
+    Ok(chunk
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|choice| choice.delta.content)
+        .filter(|content| !content.is_empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_server::spawn_stand_in_server;
+
+    fn get_test_client(base_url: String) -> ChatClient {
+        ChatClient {
+            base_url,
+            api_key: "test-key".to_string(),
+            model: "potato-1".to_string(),
+            retry: RetryPolicy::from_config(&crate::config::get_default_config()),
+        }
+    }
+
+    #[tokio::test]
+    async fn send_chat_returns_assistant_reply() {
+        let (url, server) = spawn_stand_in_server(
+            "200 OK",
+            "application/json",
+            r#"{"choices":[{"message":{"role":"assistant","content":"Hello potato"}}]}"#,
+        );
+        let messages = vec![
+            AiMessage::new(AiMessageFrom::System, "Be nice".to_string()),
+            AiMessage::new(AiMessageFrom::User, "Hi".to_string()),
+        ];
+
+        let reply = get_test_client(url).send_chat(messages).await;
+        let request = server.join().unwrap();
+
+        assert_eq!(reply, Ok("Hello potato".to_string()));
+        assert!(request.starts_with("POST /chat/completions"));
+        assert!(
+            request
+                .to_lowercase()
+                .contains("authorization: bearer test-key")
+        );
+        assert!(request.contains(r#""model":"potato-1""#));
+        assert!(request.contains(r#"{"role":"system","content":"Be nice"}"#));
+        assert!(request.contains(r#"{"role":"user","content":"Hi"}"#));
+        assert!(request.contains(r#""stream":false"#));
+    }
+
+    #[tokio::test]
+    async fn stream_chat_yields_each_delta() {
+        let (url, server) = spawn_stand_in_server(
+            "200 OK",
+            "text/event-stream",
+            concat!(
+                "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+                "data: {\"choices\":[{\"delta\":{\"content\":\"Hello\"}}]}\n\n",
+                "data: {\"choices\":[{\"delta\":{\"content\":\" potato\"}}]}\n\n",
+                "data: [DONE]\n\n"
+            ),
+        );
+
+        let chunks: Vec<Result<String, AiError>> =
+            get_test_client(url).stream_chat(vec![]).collect().await;
+        let request = server.join().unwrap();
+
+        assert_eq!(
+            chunks,
+            vec![Ok("Hello".to_string()), Ok(" potato".to_string())]
+        );
+        assert!(request.contains(r#""stream":true"#));
+    }
+
+    #[tokio::test]
+    async fn stream_chat_reports_malformed_chunks() {
+        let (url, server) = spawn_stand_in_server(
+            "200 OK",
+            "text/event-stream",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n\ndata: oops\n\n",
+        );
+
+        let chunks: Vec<Result<String, AiError>> =
+            get_test_client(url).stream_chat(vec![]).collect().await;
+        server.join().unwrap();
+
+        // The error comes after the chunks received before it
+        assert!(matches!(
+            &chunks[..],
+            [Ok(hi), Err(AiError::MalformedResponse(_))] if hi == "Hi"
+        ));
+    }
+
+    #[tokio::test]
+    async fn send_chat_reports_http_errors() {
+        let (url, server) = spawn_stand_in_server(
+            "401 Unauthorized",
+            "application/json",
+            r#"{"error":"bad key"}"#,
+        );
+
+        let reply = get_test_client(url).send_chat(vec![]).await;
+        server.join().unwrap();
+
+        assert!(matches!(reply, Err(AiError::Auth(details)) if details.contains("bad key")));
+    }
+
+    #[tokio::test]
+    async fn send_chat_requires_an_url() {
+        let reply = get_test_client("".to_string()).send_chat(vec![]).await;
+        assert!(matches!(reply, Err(AiError::Config(_))));
+    }
+
+    #[tokio::test]
+    async fn list_models_returns_model_ids() {
+        let (url, server) = spawn_stand_in_server(
+            "200 OK",
+            "application/json",
+            r#"{"object":"list","data":[{"id":"potato-1"},{"id":"potato-2"}]}"#,
+        );
+
+        let models = get_test_client(url).list_models().await;
+        let request = server.join().unwrap();
+
+        assert_eq!(
+            models,
+            Ok(vec!["potato-1".to_string(), "potato-2".to_string()])
+        );
+        assert!(request.starts_with("GET /models"));
+    }
+}
@@ -0,0 +1,54 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+// Everything that can go wrong while talking to an AI provider
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AiError {
+    // Nothing was sent, the settings are incomplete
+    Config(String),
+    Auth(String),
+    RateLimited(String),
+    Network(String),
+    MalformedResponse(String),
+    // Any other failure reported by the provider (server error, refused answer...)
+    Provider(String),
+    Cancelled,
+}
+
+impl AiError {
+    pub fn from_status(status: reqwest::StatusCode, details: String) -> Self {
+        let message = format!("{}: {}", status, details);
+        match status.as_u16() {
+            401 | 403 => AiError::Auth(message),
+            429 => AiError::RateLimited(message),
+            _ => AiError::Provider(message),
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            AiError::Config(_) => "Configuration error",
+            AiError::Auth(_) => "Authentication failed",
+            AiError::RateLimited(_) => "Rate limited",
+            AiError::Network(_) => "Network error",
+            AiError::MalformedResponse(_) => "Malformed response",
+            AiError::Provider(_) => "Provider error",
+            AiError::Cancelled => "Cancelled",
+        }
+    }
+}
+
+impl fmt::Display for AiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AiError::Config(details)
+            | AiError::Auth(details)
+            | AiError::RateLimited(details)
+            | AiError::Network(details)
+            | AiError::MalformedResponse(details)
+            | AiError::Provider(details) => write!(f, "{}: {}", self.title(), details),
+            AiError::Cancelled => write!(f, "Request cancelled"),
+        }
+    }
+}
@@ -0,0 +1,7 @@
+pub mod client;
+pub mod error;
+pub mod providers;
+pub mod retry;
+pub mod sse;
+#[cfg(test)]
+mod test_server;
@@ -0,0 +1,361 @@
+use iced::futures::{SinkExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AiMessage, AiMessageFrom,
+    api::{
+        error::AiError,
+        providers::{Cancellation, ChatFuture, ChatProvider, ChatStream, get_chat_stream},
+        retry::{RetryPolicy, send_request},
+        sse::SseParser,
+    },
+    config::AppConfig,
+};
+
+const ANTHROPIC_URL: &str = "https://api.anthropic.com/v1";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+// The Messages API requires an explicit answer length limit
+const MAX_TOKENS: u32 = 4096;
+
+#[derive(Debug, Serialize)]
+struct MessagesRequest {
+    model: String,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<AnthropicMessage>,
+    stream: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct AnthropicMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessagesResponse {
+    #[serde(default)]
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentBlockDelta {
+    delta: TextDelta,
+}
+
+#[derive(Debug, Deserialize)]
+struct TextDelta {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorEvent {
+    error: ErrorDetails,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorDetails {
+    #[serde(rename = "type")]
+    kind: String,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+// Client for the Anthropic Messages API
+#[derive(Debug, Clone)]
+pub struct AnthropicClient {
+    pub base_url: String,
+    pub api_key: String,
+    pub model: String,
+    pub retry: RetryPolicy,
+}
+
+impl AnthropicClient {
+    pub fn from_config(config: &AppConfig) -> Self {
+        let base_url = if config.ai_url.is_empty() {
+            ANTHROPIC_URL
+        } else {
+            config.ai_url.as_str()
+        };
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone(),
+            model: config.ai_model.clone(),
+            retry: RetryPolicy::from_config(config),
+        }
+    }
+
+    fn with_headers(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        request
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+    }
+
+    fn build_messages_request(
+        &self,
+        messages: &[AiMessage],
+        stream: bool,
+    ) -> reqwest::RequestBuilder {
+        // System prompts are not part of the messages list with Anthropic
+        let system: Vec<&str> = messages
+            .iter()
+            .filter(|m| m.from == AiMessageFrom::System)
+            .map(|m| m.content.as_str())
+            .collect();
+
+        let body = MessagesRequest {
+            model: self.model.clone(),
+            max_tokens: MAX_TOKENS,
+            system: if system.is_empty() {
+                None
+            } else {
+                Some(system.join("\n\n"))
+            },
+            messages: messages
+                .iter()
+                .filter(|m| m.from != AiMessageFrom::System)
+                .map(|m| AnthropicMessage {
+                    role: match m.from {
+                        AiMessageFrom::Assistant => "assistant",
+                        _ => "user",
+                    }
+                    .to_string(),
+                    content: m.content.clone(),
+                })
+                .collect(),
+            stream,
+        };
+        self.with_headers(
+            reqwest::Client::new()
+                .post(format!("{}/messages", self.base_url))
+                .json(&body),
+        )
+    }
+
+    pub async fn send_chat(self, messages: Vec<AiMessage>) -> Result<String, AiError> {
+        let response = send_request(self.build_messages_request(&messages, false), &self.retry)
+            .await?
+            .json::<MessagesResponse>()
+            .await
+            .map_err(|e| AiError::MalformedResponse(e.to_string()))?;
+
+        let text: String = response
+            .content
+            .into_iter()
+            .filter(|block| block.kind == "text")
+            .map(|block| block.text)
+            .collect();
+        if text.is_empty() {
+            return Err(AiError::MalformedResponse(
+                "Anthropic response contains no text".to_string(),
+            ));
+        }
+        Ok(text)
+    }
+
+    // Anthropic names every SSE event, only text deltas carry the answer
+    pub fn stream_chat(
+        self,
+        messages: Vec<AiMessage>,
+    ) -> impl Stream<Item = Result<String, AiError>> {
+        get_chat_stream(move |mut output| async move {
+            let mut bytes = send_request(self.build_messages_request(&messages, true), &self.retry)
+                .await?
+                .bytes_stream();
+            let mut parser = SseParser::new();
+
+            while let Some(chunk) = bytes.next().await {
+                let chunk =
+                    chunk.map_err(|e| AiError::Network(format!("Stream interrupted: {}", e)))?;
+                for event in parser.push(&chunk) {
+                    match event.event.as_deref() {
+                        Some("content_block_delta") => {
+                            let delta = serde_json::from_str::<ContentBlockDelta>(&event.data)
+                                .map_err(|e| {
+                                    AiError::MalformedResponse(format!(
+                                        "Invalid stream chunk: {}",
+                                        e
+                                    ))
+                                })?;
+                            if !delta.delta.text.is_empty()
+                                && output.send(Ok(delta.delta.text)).await.is_err()
+                            {
+                                return Ok(());
+                            }
+                        }
+                        Some("error") => {
+                            let error = serde_json::from_str::<ErrorEvent>(&event.data)
+                                .map_err(|e| AiError::MalformedResponse(e.to_string()))?;
+                            return Err(get_stream_error(error.error));
+                        }
+                        Some("message_stop") => return Ok(()),
+                        // message_start, content_block_start, ping...
+                        _ => {}
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    pub async fn list_models(self) -> Result<Vec<String>, AiError> {
+        let request =
+            self.with_headers(reqwest::Client::new().get(format!("{}/models", self.base_url)));
+        let models = send_request(request, &self.retry)
+            .await?
+            .json::<ModelList>()
+            .await
+            .map_err(|e| AiError::MalformedResponse(e.to_string()))?;
+        Ok(models.data.into_iter().map(|m| m.id).collect())
+    }
+}
+
+// Errors sent in the middle of a stream, after the 200 status
+fn get_stream_error(error: ErrorDetails) -> AiError {
+    let message = format!("{}: {}", error.kind, error.message);
+    match error.kind.as_str() {
+        "authentication_error" | "permission_error" => AiError::Auth(message),
+        "rate_limit_error" | "overloaded_error" => AiError::RateLimited(message),
+        _ => AiError::Provider(message),
+    }
+}
+
+#[derive(Debug)]
+pub struct AnthropicProvider {
+    client: AnthropicClient,
+    cancellation: Cancellation,
+}
+
+impl AnthropicProvider {
+    pub fn new(config: &AppConfig) -> Self {
+        Self {
+            client: AnthropicClient::from_config(config),
+            cancellation: Cancellation::default(),
+        }
+    }
+}
+
+impl ChatProvider for AnthropicProvider {
+    fn send(&self, messages: Vec<AiMessage>) -> ChatFuture<String> {
+        self.cancellation
+            .guard_future(self.client.clone().send_chat(messages))
+    }
+
+    fn stream(&self, messages: Vec<AiMessage>) -> ChatStream {
+        self.cancellation
+            .guard_stream(self.client.clone().stream_chat(messages))
+    }
+
+    fn list_models(&self) -> ChatFuture<Vec<String>> {
+        self.cancellation
+            .guard_future(self.client.clone().list_models())
+    }
+
+    fn cancel(&self) {
+        self.cancellation.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_server::spawn_stand_in_server;
+
+    fn get_test_client(base_url: String) -> AnthropicClient {
+        AnthropicClient {
+            base_url,
+            api_key: "anthropic-key".to_string(),
+            model: "claude-potato".to_string(),
+            retry: RetryPolicy::from_config(&crate::config::get_default_config()),
+        }
+    }
+
+    #[tokio::test]
+    async fn send_chat_moves_system_prompt_out_of_messages() {
+        let (url, server) = spawn_stand_in_server(
+            "200 OK",
+            "application/json",
+            r#"{"id":"msg_1","type":"message","role":"assistant","content":[{"type":"text","text":"Hello potato"}]}"#,
+        );
+
+        let reply = get_test_client(url)
+            .send_chat(vec![
+                AiMessage::new(AiMessageFrom::System, "Be nice".to_string()),
+                AiMessage::new(AiMessageFrom::User, "Hi".to_string()),
+            ])
+            .await;
+        let request = server.join().unwrap();
+
+        assert_eq!(reply, Ok("Hello potato".to_string()));
+        assert!(request.starts_with("POST /messages"));
+        let headers = request.to_lowercase();
+        assert!(headers.contains("x-api-key: anthropic-key"));
+        assert!(headers.contains("anthropic-version: 2023-06-01"));
+        assert!(request.contains(r#""system":"Be nice""#));
+        assert!(request.contains(r#""messages":[{"role":"user","content":"Hi"}]"#));
+    }
+
+    #[tokio::test]
+    async fn stream_chat_keeps_only_text_deltas() {
+        let (url, server) = spawn_stand_in_server(
+            "200 OK",
+            "text/event-stream",
+            concat!(
+                "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{}}\n\n",
+                "event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":0}\n\n",
+                "event: ping\ndata: {\"type\":\"ping\"}\n\n",
+                "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hello\"}}\n\n",
+                "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\" potato\"}}\n\n",
+                "event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n"
+            ),
+        );
+
+        let chunks: Vec<Result<String, AiError>> =
+            get_test_client(url).stream_chat(vec![]).collect().await;
+        server.join().unwrap();
+
+        assert_eq!(
+            chunks,
+            vec![Ok("Hello".to_string()), Ok(" potato".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_chat_reports_error_events() {
+        let (url, server) = spawn_stand_in_server(
+            "200 OK",
+            "text/event-stream",
+            "event: error\ndata: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\n\n",
+        );
+
+        let chunks: Vec<Result<String, AiError>> =
+            get_test_client(url).stream_chat(vec![]).collect().await;
+        server.join().unwrap();
+
+        assert_eq!(
+            chunks,
+            vec![Err(AiError::RateLimited(
+                "overloaded_error: Overloaded".to_string()
+            ))]
+        );
+    }
+}
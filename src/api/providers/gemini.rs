@@ -0,0 +1,413 @@
+use iced::futures::{SinkExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AiMessage, AiMessageFrom,
+    api::{
+        error::AiError,
+        providers::{Cancellation, ChatFuture, ChatProvider, ChatStream, get_chat_stream},
+        retry::{RetryPolicy, send_request},
+        sse::SseParser,
+    },
+    config::AppConfig,
+};
+
+const GEMINI_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentRequest {
+    contents: Vec<Content>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<Content>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Content {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
+    #[serde(default)]
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Part {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    prompt_feedback: Option<PromptFeedback>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    content: Option<Content>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptFeedback {
+    block_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelList {
+    #[serde(default)]
+    models: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelEntry {
+    name: String,
+    #[serde(default)]
+    supported_generation_methods: Vec<String>,
+}
+
+// Finish reasons meaning Gemini refused to (fully) answer
+const BLOCKING_FINISH_REASONS: [&str; 5] = [
+    "SAFETY",
+    "RECITATION",
+    "BLOCKLIST",
+    "PROHIBITED_CONTENT",
+    "SPII",
+];
+
+// Client for the native Gemini generateContent API
+#[derive(Debug, Clone)]
+pub struct GeminiClient {
+    pub base_url: String,
+    pub api_key: String,
+    pub model: String,
+    pub retry: RetryPolicy,
+}
+
+impl GeminiClient {
+    pub fn from_config(config: &AppConfig) -> Self {
+        let base_url = if config.ai_url.is_empty() {
+            GEMINI_URL
+        } else {
+            config.ai_url.as_str()
+        };
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone(),
+            // The API expects the bare model id, "models/" is part of the path
+            model: config.ai_model.trim_start_matches("models/").to_string(),
+            retry: RetryPolicy::from_config(config),
+        }
+    }
+
+    fn build_request(
+        &self,
+        messages: &[AiMessage],
+        method: &str,
+    ) -> Result<reqwest::RequestBuilder, AiError> {
+        if self.model.is_empty() {
+            return Err(AiError::Config(
+                "No Gemini model configured, set ai_model in settings".to_string(),
+            ));
+        }
+        let url = format!("{}/models/{}:{}", self.base_url, self.model, method);
+        let body = to_gemini_request(messages);
+        Ok(reqwest::Client::new()
+            .post(url)
+            .header("x-goog-api-key", &self.api_key)
+            .json(&body))
+    }
+
+    pub async fn send_chat(self, messages: Vec<AiMessage>) -> Result<String, AiError> {
+        let request = self.build_request(&messages, "generateContent")?;
+        let response = send_request(request, &self.retry)
+            .await?
+            .json::<GenerateContentResponse>()
+            .await
+            .map_err(|e| AiError::MalformedResponse(e.to_string()))?;
+
+        let text = read_response(response)?;
+        if text.is_empty() {
+            return Err(AiError::MalformedResponse(
+                "Gemini response contains no text".to_string(),
+            ));
+        }
+        Ok(text)
+    }
+
+    pub fn stream_chat(
+        self,
+        messages: Vec<AiMessage>,
+    ) -> impl Stream<Item = Result<String, AiError>> {
+        get_chat_stream(move |mut output| async move {
+            let request = self
+                .build_request(&messages, "streamGenerateContent")?
+                .query(&[("alt", "sse")]);
+            let mut bytes = send_request(request, &self.retry).await?.bytes_stream();
+            let mut parser = SseParser::new();
+
+            while let Some(chunk) = bytes.next().await {
+                let chunk =
+                    chunk.map_err(|e| AiError::Network(format!("Stream interrupted: {}", e)))?;
+                for event in parser.push(&chunk) {
+                    let text = parse_stream_event(&event.data)?;
+                    if !text.is_empty() && output.send(Ok(text)).await.is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+
+            if let Some(event) = parser.finish() {
+                let text = parse_stream_event(&event.data)?;
+                if !text.is_empty() {
+                    let _ = output.send(Ok(text)).await;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    pub async fn list_models(self) -> Result<Vec<String>, AiError> {
+        let request = reqwest::Client::new()
+            .get(format!("{}/models", self.base_url))
+            .header("x-goog-api-key", &self.api_key);
+        let models = send_request(request, &self.retry)
+            .await?
+            .json::<ModelList>()
+            .await
+            .map_err(|e| AiError::MalformedResponse(e.to_string()))?;
+
+        Ok(models
+            .models
+            .into_iter()
+            .filter(|m| {
+                m.supported_generation_methods
+                    .iter()
+                    .any(|method| method == "generateContent")
+            })
+            .map(|m| m.name.trim_start_matches("models/").to_string())
+            .collect())
+    }
+}
+
+fn to_gemini_request(messages: &[AiMessage]) -> GenerateContentRequest {
+    let system_parts: Vec<Part> = messages
+        .iter()
+        .filter(|m| m.from == AiMessageFrom::System)
+        .map(|m| Part {
+            text: m.content.clone(),
+        })
+        .collect();
+
+    let contents = messages
+        .iter()
+        .filter(|m| m.from != AiMessageFrom::System)
+        .map(|m| Content {
+            role: Some(
+                match m.from {
+                    AiMessageFrom::Assistant => "model",
+                    _ => "user",
+                }
+                .to_string(),
+            ),
+            parts: vec![Part {
+                text: m.content.clone(),
+            }],
+        })
+        .collect();
+
+    GenerateContentRequest {
+        contents,
+        system_instruction: if system_parts.is_empty() {
+            None
+        } else {
+            Some(Content {
+                role: None,
+                parts: system_parts,
+            })
+        },
+    }
+}
+
+// Extract the text of the first candidate, safety blocks are turned into errors
+fn read_response(response: GenerateContentResponse) -> Result<String, AiError> {
+    if let Some(reason) = response.prompt_feedback.and_then(|f| f.block_reason) {
+        return Err(AiError::Provider(format!(
+            "Gemini blocked the prompt: {}",
+            reason
+        )));
+    }
+
+    let Some(candidate) = response.candidates.into_iter().next() else {
+        return Ok("".to_string());
+    };
+
+    if let Some(reason) = candidate.finish_reason
+        && BLOCKING_FINISH_REASONS.contains(&reason.as_str())
+    {
+        return Err(AiError::Provider(format!(
+            "Gemini stopped the answer: {}",
+            reason
+        )));
+    }
+
+    Ok(candidate
+        .content
+        .map(|c| c.parts.into_iter().map(|p| p.text).collect())
+        .unwrap_or_default())
+}
+
+fn parse_stream_event(data: &str) -> Result<String, AiError> {
+    let response = serde_json::from_str::<GenerateContentResponse>(data)
+        .map_err(|e| AiError::MalformedResponse(format!("Invalid stream chunk: {}", e)))?;
+    read_response(response)
+}
+
+#[derive(Debug)]
+pub struct GeminiProvider {
+    client: GeminiClient,
+    cancellation: Cancellation,
+}
+
+impl GeminiProvider {
+    pub fn new(config: &AppConfig) -> Self {
+        Self {
+            client: GeminiClient::from_config(config),
+            cancellation: Cancellation::default(),
+        }
+    }
+}
+
+impl ChatProvider for GeminiProvider {
+    fn send(&self, messages: Vec<AiMessage>) -> ChatFuture<String> {
+        self.cancellation
+            .guard_future(self.client.clone().send_chat(messages))
+    }
+
+    fn stream(&self, messages: Vec<AiMessage>) -> ChatStream {
+        self.cancellation
+            .guard_stream(self.client.clone().stream_chat(messages))
+    }
+
+    fn list_models(&self) -> ChatFuture<Vec<String>> {
+        self.cancellation
+            .guard_future(self.client.clone().list_models())
+    }
+
+    fn cancel(&self) {
+        self.cancellation.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_server::spawn_stand_in_server;
+
+    fn get_test_client(base_url: String) -> GeminiClient {
+        GeminiClient {
+            base_url,
+            api_key: "gemini-key".to_string(),
+            model: "gemini-potato".to_string(),
+            retry: RetryPolicy::from_config(&crate::config::get_default_config()),
+        }
+    }
+
+    fn get_test_messages() -> Vec<AiMessage> {
+        vec![
+            AiMessage::new(AiMessageFrom::System, "Be nice".to_string()),
+            AiMessage::new(AiMessageFrom::User, "Hi".to_string()),
+            AiMessage::new(AiMessageFrom::Assistant, "Hello".to_string()),
+        ]
+    }
+
+    #[tokio::test]
+    async fn send_chat_uses_native_wire_format() {
+        let (url, server) = spawn_stand_in_server(
+            "200 OK",
+            "application/json",
+            r#"{"candidates":[{"content":{"role":"model","parts":[{"text":"Hello "},{"text":"potato"}]},"finishReason":"STOP"}]}"#,
+        );
+
+        let reply = get_test_client(url).send_chat(get_test_messages()).await;
+        let request = server.join().unwrap();
+
+        assert_eq!(reply, Ok("Hello potato".to_string()));
+        assert!(request.starts_with("POST /models/gemini-potato:generateContent"));
+        assert!(
+            request
+                .to_lowercase()
+                .contains("x-goog-api-key: gemini-key")
+        );
+        assert!(request.contains(r#""systemInstruction":{"parts":[{"text":"Be nice"}]}"#));
+        assert!(request.contains(r#"{"role":"user","parts":[{"text":"Hi"}]}"#));
+        assert!(request.contains(r#"{"role":"model","parts":[{"text":"Hello"}]}"#));
+    }
+
+    #[tokio::test]
+    async fn send_chat_reports_blocked_prompt() {
+        let (url, server) = spawn_stand_in_server(
+            "200 OK",
+            "application/json",
+            r#"{"candidates":[],"promptFeedback":{"blockReason":"SAFETY"}}"#,
+        );
+
+        let reply = get_test_client(url).send_chat(get_test_messages()).await;
+        server.join().unwrap();
+
+        assert!(matches!(reply, Err(AiError::Provider(details)) if details.contains("SAFETY")));
+    }
+
+    #[tokio::test]
+    async fn stream_chat_yields_parts_and_stops_on_safety() {
+        let (url, server) = spawn_stand_in_server(
+            "200 OK",
+            "text/event-stream",
+            concat!(
+                "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"text\":\"Hel\"}]}}]}\r\n\r\n",
+                "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"text\":\"lo\"}]}}]}\r\n\r\n",
+                "data: {\"candidates\":[{\"finishReason\":\"SAFETY\"}]}\r\n\r\n"
+            ),
+        );
+
+        let chunks: Vec<Result<String, AiError>> = get_test_client(url)
+            .stream_chat(get_test_messages())
+            .collect()
+            .await;
+        let request = server.join().unwrap();
+
+        assert!(request.starts_with("POST /models/gemini-potato:streamGenerateContent?alt=sse"));
+        assert_eq!(
+            chunks,
+            vec![
+                Ok("Hel".to_string()),
+                Ok("lo".to_string()),
+                Err(AiError::Provider(
+                    "Gemini stopped the answer: SAFETY".to_string()
+                ))
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn list_models_keeps_chat_models() {
+        let (url, server) = spawn_stand_in_server(
+            "200 OK",
+            "application/json",
+            r#"{"models":[
+                {"name":"models/gemini-potato","supportedGenerationMethods":["generateContent","countTokens"]},
+                {"name":"models/embedding-potato","supportedGenerationMethods":["embedContent"]}
+            ]}"#,
+        );
+
+        let models = get_test_client(url).list_models().await;
+        server.join().unwrap();
+
+        assert_eq!(models, Ok(vec!["gemini-potato".to_string()]));
+    }
+}
@@ -0,0 +1,132 @@
+use std::{
+    fmt::Debug,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use iced::futures::{
+    FutureExt, SinkExt, Stream, StreamExt,
+    channel::mpsc,
+    future::{BoxFuture, ready},
+    stream::BoxStream,
+};
+
+use crate::{
+    AiMessage,
+    api::error::AiError,
+    config::{AiProvider, AppConfig},
+};
+
+pub mod anthropic;
+pub mod gemini;
+pub mod ollama;
+pub mod openai;
+
+pub type ChatFuture<T> = BoxFuture<'static, Result<T, AiError>>;
+pub type ChatStream = BoxStream<'static, Result<String, AiError>>;
+
+// Everything the app needs from an AI backend, one implementation per AiProvider
+pub trait ChatProvider: Debug + Send + Sync {
+    fn send(&self, messages: Vec<AiMessage>) -> ChatFuture<String>;
+    fn stream(&self, messages: Vec<AiMessage>) -> ChatStream;
+    fn list_models(&self) -> ChatFuture<Vec<String>>;
+    // Stop every request started before this call
+    fn cancel(&self);
+}
+
+pub fn get_provider(config: &AppConfig) -> Box<dyn ChatProvider> {
+    match config.ai_provider {
+        AiProvider::OpenAI => Box::new(openai::OpenAiProvider::new(config, openai::OPENAI_URL)),
+        AiProvider::Gemini => Box::new(gemini::GeminiProvider::new(config)),
+        AiProvider::Ollama => Box::new(ollama::OllamaProvider::new(config)),
+        AiProvider::Anthropic => Box::new(anthropic::AnthropicProvider::new(config)),
+        AiProvider::Custom => Box::new(openai::OpenAiProvider::new(config, "")),
+    }
+}
+
+// Stream of the chunks a request sends, then of its error if it fails. Both go through the
+// same channel, iced::stream::try_channel could deliver the error before buffered chunks.
+pub fn get_chat_stream<F>(
+    read: impl FnOnce(mpsc::Sender<Result<String, AiError>>) -> F,
+) -> impl Stream<Item = Result<String, AiError>>
+where
+    F: Future<Output = Result<(), AiError>>,
+{
+    iced::stream::channel(
+        100,
+        move |mut output: mpsc::Sender<Result<String, AiError>>| async move {
+            if let Err(e) = read(output.clone()).await {
+                let _ = output.send(Err(e)).await;
+            }
+        },
+    )
+}
+
+// Each request remembers the generation it started in, cancel() moves to the next one
+#[derive(Debug, Clone, Default)]
+pub struct Cancellation {
+    generation: Arc<AtomicU64>,
+}
+
+impl Cancellation {
+    pub fn cancel(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn guard_future<T: Send + 'static>(
+        &self,
+        future: impl Future<Output = Result<T, AiError>> + Send + 'static,
+    ) -> ChatFuture<T> {
+        let started_in = self.generation.load(Ordering::SeqCst);
+        let generation = self.generation.clone();
+        future
+            .map(move |result| {
+                if generation.load(Ordering::SeqCst) == started_in {
+                    result
+                } else {
+                    Err(AiError::Cancelled)
+                }
+            })
+            .boxed()
+    }
+
+    pub fn guard_stream(
+        &self,
+        stream: impl Stream<Item = Result<String, AiError>> + Send + 'static,
+    ) -> ChatStream {
+        let started_in = self.generation.load(Ordering::SeqCst);
+        let generation = self.generation.clone();
+        stream
+            .take_while(move |_| ready(generation.load(Ordering::SeqCst) == started_in))
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::futures::stream;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn cancel_stops_running_streams_only() {
+        let cancellation = Cancellation::default();
+        let cancelled = cancellation.guard_stream(stream::iter(vec![Ok("a".to_string())]));
+        cancellation.cancel();
+        let fresh = cancellation.guard_stream(stream::iter(vec![Ok("b".to_string())]));
+
+        assert!(cancelled.collect::<Vec<_>>().await.is_empty());
+        assert_eq!(fresh.collect::<Vec<_>>().await, vec![Ok("b".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn cancel_turns_pending_reply_into_error() {
+        let cancellation = Cancellation::default();
+        let reply = cancellation.guard_future(async { Ok("late".to_string()) });
+        cancellation.cancel();
+
+        assert_eq!(reply.await, Err(AiError::Cancelled));
+    }
+}
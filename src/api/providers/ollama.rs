@@ -0,0 +1,307 @@
+use iced::futures::{SinkExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AiMessage, AiMessageFrom,
+    api::{
+        error::AiError,
+        providers::{Cancellation, ChatFuture, ChatProvider, ChatStream, get_chat_stream},
+        retry::{RetryPolicy, send_request},
+    },
+    config::AppConfig,
+};
+
+const OLLAMA_URL: &str = "http://localhost:11434";
+
+#[derive(Debug, Serialize)]
+struct OllamaChatRequest {
+    model: String,
+    messages: Vec<OllamaMessage>,
+    stream: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaMessage {
+    role: String,
+    #[serde(default)]
+    content: String,
+}
+
+// Same shape for the full answer and for every streamed line
+#[derive(Debug, Deserialize)]
+struct OllamaChatResponse {
+    message: Option<OllamaMessage>,
+    #[serde(default)]
+    done: bool,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaTags {
+    #[serde(default)]
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaModel {
+    name: String,
+}
+
+// Client for a local Ollama server, no api key involved
+#[derive(Debug, Clone)]
+pub struct OllamaClient {
+    pub base_url: String,
+    pub model: String,
+    pub retry: RetryPolicy,
+}
+
+impl OllamaClient {
+    pub fn from_config(config: &AppConfig) -> Self {
+        let base_url = if config.ai_url.is_empty() {
+            OLLAMA_URL
+        } else {
+            config.ai_url.as_str()
+        };
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: config.ai_model.clone(),
+            retry: RetryPolicy::from_config(config),
+        }
+    }
+
+    fn build_chat_request(&self, messages: &[AiMessage], stream: bool) -> reqwest::RequestBuilder {
+        let body = OllamaChatRequest {
+            model: self.model.clone(),
+            messages: messages
+                .iter()
+                .map(|m| OllamaMessage {
+                    role: match m.from {
+                        AiMessageFrom::User => "user",
+                        AiMessageFrom::System => "system",
+                        AiMessageFrom::Assistant => "assistant",
+                    }
+                    .to_string(),
+                    content: m.content.clone(),
+                })
+                .collect(),
+            stream,
+        };
+        reqwest::Client::new()
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&body)
+    }
+
+    pub async fn send_chat(self, messages: Vec<AiMessage>) -> Result<String, AiError> {
+        let response = send_request(self.build_chat_request(&messages, false), &self.retry)
+            .await?
+            .json::<OllamaChatResponse>()
+            .await
+            .map_err(|e| AiError::MalformedResponse(e.to_string()))?;
+
+        if let Some(error) = response.error {
+            return Err(AiError::Provider(format!("Ollama error: {}", error)));
+        }
+        response.message.map(|m| m.content).ok_or_else(|| {
+            AiError::MalformedResponse("Ollama response contains no message".to_string())
+        })
+    }
+
+    // Ollama streams one JSON object per line (NDJSON), not SSE
+    pub fn stream_chat(
+        self,
+        messages: Vec<AiMessage>,
+    ) -> impl Stream<Item = Result<String, AiError>> {
+        get_chat_stream(move |mut output| async move {
+            let mut bytes = send_request(self.build_chat_request(&messages, true), &self.retry)
+                .await?
+                .bytes_stream();
+            let mut buffer: Vec<u8> = Vec::new();
+
+            while let Some(chunk) = bytes.next().await {
+                let chunk =
+                    chunk.map_err(|e| AiError::Network(format!("Stream interrupted: {}", e)))?;
+                buffer.extend_from_slice(&chunk);
+
+                while let Some(line_end) = buffer.iter().position(|b| *b == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=line_end).collect();
+                    let (content, done) = parse_line(&line)?;
+                    if let Some(content) = content
+                        && output.send(Ok(content)).await.is_err()
+                    {
+                        return Ok(());
+                    }
+                    if done {
+                        return Ok(());
+                    }
+                }
+            }
+
+            if let (Some(content), _) = parse_line(&buffer)? {
+                let _ = output.send(Ok(content)).await;
+            }
+            Ok(())
+        })
+    }
+
+    pub async fn list_models(self) -> Result<Vec<String>, AiError> {
+        let request = reqwest::Client::new().get(format!("{}/api/tags", self.base_url));
+        let tags = send_request(request, &self.retry)
+            .await?
+            .json::<OllamaTags>()
+            .await
+            .map_err(|e| AiError::MalformedResponse(e.to_string()))?;
+        Ok(tags.models.into_iter().map(|m| m.name).collect())
+    }
+}
+
+// Returns the text carried by the line and whether it is the last one
+fn parse_line(line: &[u8]) -> Result<(Option<String>, bool), AiError> {
+    let line = String::from_utf8_lossy(line);
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok((None, false));
+    }
+
+    let response = serde_json::from_str::<OllamaChatResponse>(line)
+        .map_err(|e| AiError::MalformedResponse(format!("Invalid stream chunk: {}", e)))?;
+    if let Some(error) = response.error {
+        return Err(AiError::Provider(format!("Ollama error: {}", error)));
+    }
+    let content = response
+        .message
+        .map(|m| m.content)
+        .filter(|content| !content.is_empty());
+    Ok((content, response.done))
+}
+
+#[derive(Debug)]
+pub struct OllamaProvider {
+    client: OllamaClient,
+    cancellation: Cancellation,
+}
+
+impl OllamaProvider {
+    pub fn new(config: &AppConfig) -> Self {
+        Self {
+            client: OllamaClient::from_config(config),
+            cancellation: Cancellation::default(),
+        }
+    }
+}
+
+impl ChatProvider for OllamaProvider {
+    fn send(&self, messages: Vec<AiMessage>) -> ChatFuture<String> {
+        self.cancellation
+            .guard_future(self.client.clone().send_chat(messages))
+    }
+
+    fn stream(&self, messages: Vec<AiMessage>) -> ChatStream {
+        self.cancellation
+            .guard_stream(self.client.clone().stream_chat(messages))
+    }
+
+    fn list_models(&self) -> ChatFuture<Vec<String>> {
+        self.cancellation
+            .guard_future(self.client.clone().list_models())
+    }
+
+    fn cancel(&self) {
+        self.cancellation.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_server::spawn_stand_in_server;
+
+    fn get_test_client(base_url: String) -> OllamaClient {
+        OllamaClient {
+            base_url,
+            model: "llama-potato".to_string(),
+            retry: RetryPolicy::from_config(&crate::config::get_default_config()),
+        }
+    }
+
+    #[tokio::test]
+    async fn send_chat_returns_message_without_auth() {
+        let (url, server) = spawn_stand_in_server(
+            "200 OK",
+            "application/json",
+            r#"{"model":"llama-potato","message":{"role":"assistant","content":"Hello potato"},"done":true}"#,
+        );
+
+        let reply = get_test_client(url)
+            .send_chat(vec![AiMessage::new(AiMessageFrom::User, "Hi".to_string())])
+            .await;
+        let request = server.join().unwrap();
+
+        assert_eq!(reply, Ok("Hello potato".to_string()));
+        assert!(request.starts_with("POST /api/chat"));
+        assert!(!request.to_lowercase().contains("authorization"));
+        assert!(request.contains(r#""stream":false"#));
+    }
+
+    #[tokio::test]
+    async fn stream_chat_reads_ndjson_until_done() {
+        let (url, server) = spawn_stand_in_server(
+            "200 OK",
+            "application/x-ndjson",
+            concat!(
+                "{\"message\":{\"role\":\"assistant\",\"content\":\"Hello\"},\"done\":false}\n",
+                "{\"message\":{\"role\":\"assistant\",\"content\":\" potato\"},\"done\":false}\n",
+                "{\"message\":{\"role\":\"assistant\",\"content\":\"\"},\"done\":true}\n"
+            ),
+        );
+
+        let chunks: Vec<Result<String, AiError>> =
+            get_test_client(url).stream_chat(vec![]).collect().await;
+        server.join().unwrap();
+
+        assert_eq!(
+            chunks,
+            vec![Ok("Hello".to_string()), Ok(" potato".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_chat_reports_ollama_errors() {
+        let (url, server) = spawn_stand_in_server(
+            "200 OK",
+            "application/x-ndjson",
+            "{\"error\":\"model not found\"}\n",
+        );
+
+        let chunks: Vec<Result<String, AiError>> =
+            get_test_client(url).stream_chat(vec![]).collect().await;
+        server.join().unwrap();
+
+        assert_eq!(
+            chunks,
+            vec![Err(AiError::Provider(
+                "Ollama error: model not found".to_string()
+            ))]
+        );
+    }
+
+    #[tokio::test]
+    async fn list_models_reads_local_tags() {
+        let (url, server) = spawn_stand_in_server(
+            "200 OK",
+            "application/json",
+            r#"{"models":[{"name":"llama-potato:latest"},{"name":"mistral:7b"}]}"#,
+        );
+
+        let models = get_test_client(url).list_models().await;
+        let request = server.join().unwrap();
+
+        assert_eq!(
+            models,
+            Ok(vec![
+                "llama-potato:latest".to_string(),
+                "mistral:7b".to_string()
+            ])
+        );
+        assert!(request.starts_with("GET /api/tags"));
+    }
+}
@@ -0,0 +1,48 @@
+use crate::{
+    AiMessage,
+    api::{
+        client::ChatClient,
+        providers::{Cancellation, ChatFuture, ChatProvider, ChatStream},
+    },
+    config::AppConfig,
+};
+
+pub const OPENAI_URL: &str = "https://api.openai.com/v1";
+
+// OpenAI hosted API or any compatible server, ai_url replaces the default URL when set
+#[derive(Debug)]
+pub struct OpenAiProvider {
+    client: ChatClient,
+    cancellation: Cancellation,
+}
+
+impl OpenAiProvider {
+    // An empty default_url makes ai_url mandatory
+    pub fn new(config: &AppConfig, default_url: &str) -> Self {
+        Self {
+            client: ChatClient::from_config(config, default_url),
+            cancellation: Cancellation::default(),
+        }
+    }
+}
+
+impl ChatProvider for OpenAiProvider {
+    fn send(&self, messages: Vec<AiMessage>) -> ChatFuture<String> {
+        self.cancellation
+            .guard_future(self.client.clone().send_chat(messages))
+    }
+
+    fn stream(&self, messages: Vec<AiMessage>) -> ChatStream {
+        self.cancellation
+            .guard_stream(self.client.clone().stream_chat(messages))
+    }
+
+    fn list_models(&self) -> ChatFuture<Vec<String>> {
+        self.cancellation
+            .guard_future(self.client.clone().list_models())
+    }
+
+    fn cancel(&self) {
+        self.cancellation.cancel();
+    }
+}
@@ -0,0 +1,203 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+use reqwest::{RequestBuilder, Response, StatusCode, header::RETRY_AFTER};
+
+use crate::{api::error::AiError, config::AppConfig};
+
+// Never wait longer than this between two attempts, whatever the backoff says
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    // Maximum time to wait for the server to start answering
+    pub timeout: Duration,
+    pub max_retries: u32,
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            timeout: Duration::from_secs(config.request_timeout_secs),
+            max_retries: config.max_retries,
+            base_delay: Duration::from_millis(config.retry_base_delay_ms),
+        }
+    }
+
+    // Exponential backoff with up to 50% of random jitter so clients do not retry in sync
+    pub fn backoff_delay(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(MAX_RETRY_DELAY);
+        let jitter_ms = (exponential.as_millis() as u64 / 2).max(1);
+        let jitter = Duration::from_millis(random_u64() % jitter_ms);
+        (exponential + jitter).min(MAX_RETRY_DELAY)
+    }
+}
+
+// Random enough for jitter without pulling a rand crate
+fn random_u64() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default(),
+    );
+    hasher.finish()
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(status.as_u16(), 429 | 500 | 502 | 503 | 504)
+}
+
+// Only the delay-seconds form is supported, HTTP dates fall back to the backoff
+fn get_retry_after(response: &Response) -> Option<Duration> {
+    if !matches!(
+        response.status(),
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+    ) {
+        return None;
+    }
+    response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(|secs| Duration::from_secs(secs).min(MAX_RETRY_DELAY))
+}
+
+// Send the request, retrying network failures, rate limits and server errors
+pub async fn send_request(
+    request: RequestBuilder,
+    policy: &RetryPolicy,
+) -> Result<Response, AiError> {
+    let mut attempt = 0;
+    loop {
+        let Some(attempt_request) = request.try_clone() else {
+            return Err(AiError::Config(
+                "Request body cannot be sent twice".to_string(),
+            ));
+        };
+
+        let (error, retry_after) =
+            match tokio::time::timeout(policy.timeout, attempt_request.send()).await {
+                Err(_) => (
+                    AiError::Network(format!(
+                        "No answer after {} seconds",
+                        policy.timeout.as_secs()
+                    )),
+                    None,
+                ),
+                Ok(Err(e)) => (AiError::Network(e.to_string()), None),
+                Ok(Ok(response)) if response.status().is_success() => return Ok(response),
+                Ok(Ok(response)) => {
+                    let status = response.status();
+                    let retry_after = get_retry_after(&response);
+                    let details = response.text().await.unwrap_or_default();
+                    let error = AiError::from_status(status, details);
+                    if !is_retryable_status(status) {
+                        return Err(error);
+                    }
+                    (error, retry_after)
+                }
+            };
+
+        if attempt >= policy.max_retries {
+            return Err(error);
+        }
+        let delay = retry_after.unwrap_or_else(|| policy.backoff_delay(attempt));
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_server::spawn_stand_in_server_sequence;
+
+    fn get_test_policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            timeout: Duration::from_secs(5),
+            max_retries,
+            base_delay: Duration::from_millis(1),
+        }
+    }
+
+    #[test]
+    fn backoff_grows_and_stays_capped() {
+        let policy = RetryPolicy {
+            timeout: Duration::from_secs(5),
+            max_retries: 10,
+            base_delay: Duration::from_millis(100),
+        };
+
+        let first = policy.backoff_delay(0);
+        assert!(first >= Duration::from_millis(100) && first < Duration::from_millis(150));
+        let third = policy.backoff_delay(2);
+        assert!(third >= Duration::from_millis(400) && third < Duration::from_millis(600));
+        assert_eq!(policy.backoff_delay(30), MAX_RETRY_DELAY);
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_until_success() {
+        let (url, server) = spawn_stand_in_server_sequence(vec![
+            ("503 Service Unavailable", "Retry-After: 0", "busy"),
+            ("500 Internal Server Error", "", "oops"),
+            ("200 OK", "", "done"),
+        ]);
+
+        let response = send_request(reqwest::Client::new().get(url), &get_test_policy(3)).await;
+        let requests = server.join().unwrap();
+
+        assert_eq!(response.unwrap().text().await.unwrap(), "done");
+        assert_eq!(requests.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let (url, server) = spawn_stand_in_server_sequence(vec![
+            ("429 Too Many Requests", "Retry-After: 0", "slow down"),
+            ("429 Too Many Requests", "Retry-After: 0", "slow down"),
+        ]);
+
+        let response = send_request(reqwest::Client::new().get(url), &get_test_policy(1)).await;
+        server.join().unwrap();
+
+        assert!(matches!(response, Err(AiError::RateLimited(_))));
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_auth_failures() {
+        let (url, server) =
+            spawn_stand_in_server_sequence(vec![("401 Unauthorized", "", "bad key")]);
+
+        let response = send_request(reqwest::Client::new().get(url), &get_test_policy(3)).await;
+        let requests = server.join().unwrap();
+
+        assert!(matches!(response, Err(AiError::Auth(details)) if details.contains("bad key")));
+        assert_eq!(requests.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn reports_unreachable_server_as_network_error() {
+        // Nothing listens on this port once the listener is dropped
+        let url = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}", listener.local_addr().unwrap())
+        };
+
+        let response = send_request(reqwest::Client::new().get(url), &get_test_policy(0)).await;
+
+        assert!(matches!(response, Err(AiError::Network(_))));
+    }
+}
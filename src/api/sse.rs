@@ -0,0 +1,137 @@
+// Incremental parser for Server-Sent Events (text/event-stream)
+// Bytes can be pushed as they arrive, complete events are returned as soon as
+// their terminating blank line has been received.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SseEvent {
+    pub event: Option<String>,
+    pub data: String,
+}
+
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = Vec::new();
+
+        // Only consume complete lines, a partial line stays in the buffer
+        while let Some(line_end) = self.buffer.iter().position(|b| *b == b'\n') {
+            let raw_line: Vec<u8> = self.buffer.drain(..=line_end).collect();
+            let line = String::from_utf8_lossy(&raw_line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                if let Some(event) = self.dispatch() {
+                    events.push(event);
+                }
+                continue;
+            }
+
+            // Lines starting with a colon are comments (often used as keep-alive)
+            if line.starts_with(':') {
+                continue;
+            }
+
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (line, ""),
+            };
+
+            match field {
+                "event" => self.event = Some(value.to_string()),
+                "data" => self.data.push(value.to_string()),
+                _ => {}
+            }
+        }
+
+        events
+    }
+
+    // Flush the pending event when the stream ends without a trailing blank line
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        if !self.buffer.is_empty() {
+            let mut rest = std::mem::take(&mut self.buffer);
+            rest.push(b'\n');
+            self.push(&rest);
+        }
+        self.dispatch()
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        if self.data.is_empty() {
+            self.event = None;
+            return None;
+        }
+        Some(SseEvent {
+            event: self.event.take(),
+            data: std::mem::take(&mut self.data).join("\n"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_events_split_across_chunks() {
+        let mut parser = SseParser::new();
+
+        assert!(parser.push(b"data: {\"a\"").is_empty());
+        let events = parser.push(b":1}\n\ndata: [DONE]\n\n");
+
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: None,
+                    data: "{\"a\":1}".to_string()
+                },
+                SseEvent {
+                    event: None,
+                    data: "[DONE]".to_string()
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_event_names_and_multiline_data() {
+        let mut parser = SseParser::new();
+
+        let events = parser.push(b": ping\r\nevent: delta\r\ndata: one\r\ndata: two\r\n\r\n");
+
+        assert_eq!(
+            events,
+            vec![SseEvent {
+                event: Some("delta".to_string()),
+                data: "one\ntwo".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn finish_flushes_unterminated_event() {
+        let mut parser = SseParser::new();
+
+        assert!(parser.push(b"data: last").is_empty());
+
+        assert_eq!(
+            parser.finish(),
+            Some(SseEvent {
+                event: None,
+                data: "last".to_string()
+            })
+        );
+    }
+}
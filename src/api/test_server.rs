@@ -0,0 +1,85 @@
+// Stand-in HTTP server used by the api tests instead of a real AI provider
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+// Minimal HTTP server answering a single request with the given status and body.
+// Returns the server url and a handle giving back the raw request received.
+pub fn spawn_stand_in_server(
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> (String, thread::JoinHandle<String>) {
+    let header = format!("Content-Type: {}", content_type);
+    let (url, handle) = spawn_stand_in_server_sequence(vec![(status, header.as_str(), body)]);
+    (url, thread::spawn(move || handle.join().unwrap().remove(0)))
+}
+
+// Answer one request per (status, extra header line, body) entry, in order.
+// The handle gives back every raw request received.
+pub fn spawn_stand_in_server_sequence(
+    responses: Vec<(&str, &str, &str)>,
+) -> (String, thread::JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let responses: Vec<String> = responses
+        .into_iter()
+        .map(|(status, header, body)| {
+            let header = if header.is_empty() {
+                "".to_string()
+            } else {
+                format!("{}\r\n", header)
+            };
+            format!(
+                "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                header,
+                body.len(),
+                body
+            )
+        })
+        .collect();
+
+    let handle = thread::spawn(move || {
+        responses
+            .into_iter()
+            .map(|response| {
+                let (mut socket, _) = listener.accept().unwrap();
+                let request = read_request(&mut socket);
+                socket.write_all(response.as_bytes()).unwrap();
+                request
+            })
+            .collect()
+    });
+
+    (url, handle)
+}
+
+fn read_request(socket: &mut TcpStream) -> String {
+    let mut request = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        let read = socket.read(&mut chunk).unwrap();
+        request.extend_from_slice(&chunk[..read]);
+        let text = String::from_utf8_lossy(&request);
+        if let Some(header_end) = text.find("\r\n\r\n") {
+            let content_length = text[..header_end]
+                .lines()
+                .find_map(|l| {
+                    l.to_lowercase()
+                        .strip_prefix("content-length:")
+                        .map(|v| v.trim().parse::<usize>().unwrap())
+                })
+                .unwrap_or(0);
+            if request.len() >= header_end + 4 + content_length {
+                break;
+            }
+        }
+        if read == 0 {
+            break;
+        }
+    }
+    String::from_utf8_lossy(&request).to_string()
+}
@@ -1,6 +1,56 @@
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{
+    FromSample, I24, Sample, SampleFormat, SizedSample,
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+};
+use std::collections::VecDeque;
 use std::fmt;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{
+    audio::{
+        resample::{WHISPER_SAMPLE_RATE, downmix_to_mono, resample_to_16k},
+        vad::{VoiceDetector, trim_leading_silence},
+    },
+    config::AppConfig,
+};
+
+// Length of audio the level meter looks at
+const LEVEL_WINDOW: Duration = Duration::from_millis(100);
+// Levels below this are shown as an empty meter
+const METER_FLOOR_DB: f32 = -60.0;
+
+// Loudness of the last moments of the recording, samples between -1 and 1
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MicLevel {
+    pub rms: f32,
+    pub peak: f32,
+}
+
+impl MicLevel {
+    pub fn measure(samples: &[f32]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        Self {
+            rms: (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt(),
+            peak: samples.iter().fold(0.0, |peak, s| s.abs().max(peak)),
+        }
+    }
+
+    // RMS on a decibel scale mapped to 0..1, closer to how loud it sounds
+    pub fn get_meter(&self) -> f32 {
+        (1.0 - to_db(self.rms) / METER_FLOOR_DB).clamp(0.0, 1.0)
+    }
+
+    pub fn get_peak_db(&self) -> f32 {
+        to_db(self.peak).max(METER_FLOOR_DB)
+    }
+}
+
+fn to_db(value: f32) -> f32 {
+    20.0 * value.max(1e-6).log10()
+}
 
 // 1. Structure to manage the microphone state
 pub struct AudioRecorder {
@@ -8,14 +58,30 @@ pub struct AudioRecorder {
     // Thread-safe buffer to store audio samples
     // Arc = Shared ownership, Mutex = Safe access from multiple threads
     audio_buffer: Arc<Mutex<Vec<f32>>>,
+    // Set by the audio thread when the device fails, e.g. when it is unplugged
+    stream_error: Arc<Mutex<Option<String>>>,
+    // Device name from the settings, empty for the system default
+    input_device: String,
+    // Minimum level of speech for the voice activity detection
+    vad_threshold: f32,
+    // Follows the recording to notice when the user stops talking
+    vad: VoiceDetector,
+    // Samples of the buffer already given to the detector
+    vad_position: usize,
+    started_at: Option<Instant>,
+    // Format of the device used by the last recording
     config: cpal::StreamConfig,
+    // Format the device delivers, converted to f32 as it arrives
+    sample_format: SampleFormat,
     pub recording: bool,
 }
 
 impl fmt::Debug for AudioRecorder {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("AudioRecorder")
+            .field("input_device", &self.input_device)
             .field("config", &self.config)
+            .field("sample_format", &self.sample_format)
             .field(
                 "audio_buffer",
                 &format!(
@@ -38,137 +104,388 @@ impl fmt::Debug for AudioRecorder {
 }
 
 impl AudioRecorder {
-    pub fn new() -> Result<Self, String> {
-        let host = cpal::default_host();
-
-        // Get default input device (Microphone)
-        let device = host
-            .default_input_device()
-            .ok_or("No input device available")?;
-
-        // Get default config (Sample rate, channels...)
-        let config: cpal::StreamConfig = device
-            .default_input_config()
-            .map_err(|e| e.to_string())?
-            .into();
-
-        Ok(Self {
+    // The device is only opened when recording, so a missing microphone is not an error here
+    pub fn new(app_config: &AppConfig) -> Self {
+        Self {
             stream: None,
             audio_buffer: Arc::new(Mutex::new(Vec::new())),
-            config,
+            stream_error: Arc::new(Mutex::new(None)),
+            input_device: app_config.input_device.clone(),
+            vad_threshold: app_config.vad_threshold,
+            vad: VoiceDetector::new(WHISPER_SAMPLE_RATE, app_config.vad_threshold),
+            vad_position: 0,
+            started_at: None,
+            config: cpal::StreamConfig {
+                channels: 1,
+                sample_rate: cpal::SampleRate(16000),
+                buffer_size: cpal::BufferSize::Default,
+            },
+            sample_format: SampleFormat::F32,
             recording: false,
-        })
+        }
+    }
+
+    // Settings used from the next recording on
+    pub fn set_config(&mut self, app_config: &AppConfig) {
+        self.input_device = app_config.input_device.clone();
+        self.vad_threshold = app_config.vad_threshold;
     }
 
     // 2. Start Recording
     pub fn start(&mut self) -> Result<(), String> {
-        let host = cpal::default_host();
-        let device = host.default_input_device().ok_or("No input device")?;
-
-        // Clone the arc to pass it into the audio thread closure
-        let buffer_clone = self.audio_buffer.clone();
-
-        // Reset buffer
-        buffer_clone.lock().unwrap().clear();
-
-        let err_fn = |err| eprintln!("an error occurred on stream: {}", err);
-
-        // Build the input stream
-        // This closure runs continuously on a separate thread!
-        let stream = device
-            .build_input_stream(
-                &self.config,
-                move |data: &[f32], _: &_| {
-                    // Determine silence/noise threshold (simple gate)
-                    // This prevents recording pure silence, but optional.
-
-                    // Write data to the shared buffer
-                    if let Ok(mut buffer) = buffer_clone.lock() {
-                        buffer.extend_from_slice(data);
-                    }
-                },
-                err_fn,
-                None, // None = blocking timeout
-            )
-            .map_err(|e| e.to_string())?;
-
-        stream.play().map_err(|e| e.to_string())?;
-        self.stream = Some(stream);
+        // The previous recording is cleared before the first samples arrive
+        let input = start_input_stream(
+            &self.input_device,
+            self.audio_buffer.clone(),
+            self.stream_error.clone(),
+            |buffer, _| buffer.clear(),
+        )?;
+        self.sample_format = input.sample_format;
+        self.config = input.config;
+        self.vad = VoiceDetector::new(self.config.sample_rate.0, self.vad_threshold);
+        self.vad_position = 0;
+        self.stream = Some(input.stream);
         self.recording = true;
+        self.started_at = Some(Instant::now());
 
         println!("🎙️ Recording started...");
         Ok(())
     }
 
-    // 3. Stop Recording and return WAV data
-    pub fn stop(&mut self) -> Result<Vec<u8>, String> {
+    // 3. Stop Recording and return the mono 16 kHz samples recorded since `from`, ready for Whisper
+    pub fn stop(&mut self, from: Duration) -> Result<Vec<f32>, String> {
         // Drop the stream to stop recording hardware access
         self.stream = None;
         self.recording = false;
+        self.started_at = None;
         println!("🛑 Recording stopped.");
 
-        // Retrieve the raw data
-        let raw_samples = self.audio_buffer.lock().unwrap().clone();
-
-        if raw_samples.is_empty() {
+        if self.audio_buffer.lock().unwrap().is_empty() {
             return Err("No audio recorded".to_string());
         }
 
-        // Convert raw f32 samples to WAV format (bytes)
-        // OpenAI API requires a valid file format (WAV, MP3), not raw PCM
-        self.create_wav_in_memory(&raw_samples)
+        let samples = self.get_samples(from);
+        let speech = trim_leading_silence(&samples, WHISPER_SAMPLE_RATE, self.vad_threshold);
+        if speech.is_empty() {
+            return Err("No speech detected".to_string());
+        }
+        Ok(speech.to_vec())
     }
 
-    // Helper: Converts raw f32 samples to a WAV byte array
-    fn create_wav_in_memory(&self, samples: &[f32]) -> Result<Vec<u8>, String> {
-        let spec = hound::WavSpec {
-            channels: self.config.channels,
-            sample_rate: self.config.sample_rate.0,
-            bits_per_sample: 16,
-            sample_format: hound::SampleFormat::Int,
+    // Mono 16 kHz samples recorded since `from`, the recording keeps going
+    pub fn get_samples(&self, from: Duration) -> Vec<f32> {
+        let channels = self.config.channels.max(1) as usize;
+        let start = (from.as_secs_f64() * self.config.sample_rate.0 as f64) as usize * channels;
+        let raw_samples = {
+            let buffer = self.audio_buffer.lock().unwrap();
+            buffer[start.min(buffer.len())..].to_vec()
         };
+        let mono = downmix_to_mono(&raw_samples, self.config.channels);
+        resample_to_16k(&mono, self.config.sample_rate.0)
+    }
+
+    pub fn speech_started(&self) -> bool {
+        self.vad.speech_started()
+    }
 
-        // Create a cursor (in-memory file)
-        let mut cursor = std::io::Cursor::new(Vec::new());
+    // Silence since the user last spoke, zero until they start speaking
+    pub fn get_trailing_silence(&mut self) -> Duration {
+        let new_samples = {
+            let buffer = self.audio_buffer.lock().unwrap();
+            buffer[self.vad_position.min(buffer.len())..].to_vec()
+        };
+        // Only whole frames, the rest is analysed with the next samples
+        let channels = self.config.channels.max(1) as usize;
+        let frames_len = new_samples.len() / channels * channels;
+        self.vad_position += frames_len;
+        self.vad.push(&downmix_to_mono(
+            &new_samples[..frames_len],
+            self.config.channels,
+        ));
+        self.vad.trailing_silence()
+    }
 
-        {
-            let mut writer = hound::WavWriter::new(&mut cursor, spec).map_err(|e| e.to_string())?;
+    // Forget the speech heard so far, detection starts again from now.
+    // Returns how much was recorded until now.
+    pub fn restart_detection(&mut self) -> Duration {
+        let channels = self.config.channels.max(1) as usize;
+        let recorded = self.audio_buffer.lock().unwrap().len() / channels * channels;
+        self.vad = VoiceDetector::new(self.config.sample_rate.0, self.vad_threshold);
+        self.vad_position = recorded;
+        Duration::from_secs_f64(
+            (recorded / channels) as f64 / self.config.sample_rate.0.max(1) as f64,
+        )
+    }
 
-            for &sample in samples {
-                // Convert f32 (-1.0 to 1.0) to i16 (-32768 to 32767)
-                let amplitude = i16::MAX as f32;
-                let sample_i16 = (sample * amplitude) as i16;
-                writer.write_sample(sample_i16).map_err(|e| e.to_string())?;
-            }
-            writer.finalize().map_err(|e| e.to_string())?;
+    pub fn get_elapsed(&self) -> Duration {
+        self.started_at
+            .map(|started_at| started_at.elapsed())
+            .unwrap_or_default()
+    }
+
+    // Level of the most recent samples, all channels mixed
+    pub fn get_level(&self) -> MicLevel {
+        let window = (LEVEL_WINDOW.as_secs_f32()
+            * self.config.sample_rate.0 as f32
+            * self.config.channels as f32) as usize;
+        let buffer = self.audio_buffer.lock().unwrap();
+        MicLevel::measure(&buffer[buffer.len().saturating_sub(window)..])
+    }
+
+    // Error raised by the device since the recording started, if any
+    pub fn take_stream_error(&self) -> Option<String> {
+        self.stream_error.lock().unwrap().take()
+    }
+}
+
+// Names of the microphones currently plugged in
+pub fn list_input_devices() -> Vec<String> {
+    match cpal::default_host().input_devices() {
+        Ok(devices) => devices.filter_map(|device| device.name().ok()).collect(),
+        Err(e) => {
+            println!("Cannot list input devices: {}", e);
+            vec![]
         }
+    }
+}
 
-        // Return the inner buffer (the WAV file bytes)
-        Ok(cursor.into_inner())
+// The configured device, or the default one when it is not plugged in
+fn get_input_device(name: &str) -> Result<cpal::Device, String> {
+    let host = cpal::default_host();
+    if !name.is_empty() {
+        let device = host
+            .input_devices()
+            .map_err(|e| e.to_string())?
+            .find(|device| device.name().is_ok_and(|n| n == name));
+        match device {
+            Some(device) => return Ok(device),
+            None => println!("Input device {} not found, using the default one", name),
+        }
+    }
+    host.default_input_device()
+        .ok_or_else(|| "No microphone found".to_string())
+}
+
+// Where the audio thread writes the samples it receives
+pub trait SampleSink: Send + 'static {
+    fn extend_samples(&mut self, samples: impl Iterator<Item = f32>);
+}
+
+// Keeps the whole recording
+impl SampleSink for Vec<f32> {
+    fn extend_samples(&mut self, samples: impl Iterator<Item = f32>) {
+        self.extend(samples);
     }
+}
+
+// Keeps only the most recent samples, for listening without end
+#[derive(Debug, Default)]
+pub struct RingBuffer {
+    samples: VecDeque<f32>,
+    capacity: usize,
+}
 
-    // Dans audio/micro.rs
-    // Ajoute cette fonction helper pour convertir de InputRate -> 16000Hz
-    fn resample_to_16k(input_data: &[f32], input_rate: u32) -> Vec<f32> {
-        if input_rate == 16000 {
-            return input_data.to_vec();
+impl RingBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
         }
+    }
 
-        // Calcul simple ou utilisation de la crate 'rubato' pour la qualité pro.
-        // Pour un "Potato Assistant", une méthode naïve (sauter des échantillons)
-        // peut marcher si la qualité est bonne, mais l'interpolation linéaire est mieux.
+    pub fn to_vec(&self) -> Vec<f32> {
+        self.samples.iter().copied().collect()
+    }
 
-        let ratio = input_rate as f32 / 16000.0;
-        let new_len = (input_data.len() as f32 / ratio) as usize;
-        let mut output = Vec::with_capacity(new_len);
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
+
+impl SampleSink for RingBuffer {
+    fn extend_samples(&mut self, samples: impl Iterator<Item = f32>) {
+        self.samples.extend(samples);
+        let excess = self.samples.len().saturating_sub(self.capacity);
+        self.samples.drain(..excess);
+    }
+}
+
+// Microphone stream and the format the device delivers
+pub struct InputStream {
+    pub stream: cpal::Stream,
+    pub config: cpal::StreamConfig,
+    pub sample_format: SampleFormat,
+}
+
+// Open the configured device with its default format (sample rate, channels...).
+// `reset` prepares the buffer for that format before the first samples arrive,
+// and the error of the previous stream is forgotten.
+pub fn start_input_stream<B: SampleSink>(
+    input_device: &str,
+    buffer: Arc<Mutex<B>>,
+    error: Arc<Mutex<Option<String>>>,
+    reset: impl FnOnce(&mut B, &cpal::StreamConfig),
+) -> Result<InputStream, String> {
+    let device = get_input_device(input_device)?;
+    let supported_config = device.default_input_config().map_err(|e| e.to_string())?;
+    let sample_format = supported_config.sample_format();
+    let config: cpal::StreamConfig = supported_config.into();
+    *error.lock().unwrap() = None;
+    reset(&mut buffer.lock().unwrap(), &config);
+
+    // The callback runs continuously on a separate thread!
+    let stream = open_input_stream(&device, sample_format, &config, buffer, error)?;
+    Ok(InputStream {
+        stream,
+        config,
+        sample_format,
+    })
+}
+
+// Started input stream of the device, converting its sample format to f32
+fn open_input_stream<B: SampleSink>(
+    device: &cpal::Device,
+    format: SampleFormat,
+    config: &cpal::StreamConfig,
+    buffer: Arc<Mutex<B>>,
+    error: Arc<Mutex<Option<String>>>,
+) -> Result<cpal::Stream, String> {
+    let stream = match format {
+        SampleFormat::I8 => build_input_stream::<i8, B>(device, config, buffer, error),
+        SampleFormat::I16 => build_input_stream::<i16, B>(device, config, buffer, error),
+        SampleFormat::I24 => build_input_stream::<I24, B>(device, config, buffer, error),
+        SampleFormat::I32 => build_input_stream::<i32, B>(device, config, buffer, error),
+        SampleFormat::I64 => build_input_stream::<i64, B>(device, config, buffer, error),
+        SampleFormat::U8 => build_input_stream::<u8, B>(device, config, buffer, error),
+        SampleFormat::U16 => build_input_stream::<u16, B>(device, config, buffer, error),
+        SampleFormat::U32 => build_input_stream::<u32, B>(device, config, buffer, error),
+        SampleFormat::U64 => build_input_stream::<u64, B>(device, config, buffer, error),
+        SampleFormat::F32 => build_input_stream::<f32, B>(device, config, buffer, error),
+        SampleFormat::F64 => build_input_stream::<f64, B>(device, config, buffer, error),
+        format => Err(format!("Unsupported sample format: {}", format)),
+    }?;
+    stream.play().map_err(|e| e.to_string())?;
+    Ok(stream)
+}
 
-        for i in 0..new_len {
-            let index = (i as f32 * ratio) as usize;
-            if index < input_data.len() {
-                output.push(input_data[index]);
-            }
+// Input stream writing the samples of the device, whatever their format, as f32
+fn build_input_stream<T, B>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    buffer: Arc<Mutex<B>>,
+    error: Arc<Mutex<Option<String>>>,
+) -> Result<cpal::Stream, String>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+    B: SampleSink,
+{
+    // Runs on the audio thread, the app picks the error up and stops the recording
+    let err_fn = move |err: cpal::StreamError| {
+        eprintln!("an error occurred on stream: {}", err);
+        if let Ok(mut error) = error.lock() {
+            *error = Some(err.to_string());
         }
+    };
+
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _: &_| {
+                // Write data to the shared buffer
+                if let Ok(mut buffer) = buffer.lock() {
+                    buffer.extend_samples(samples_to_f32(data));
+                }
+            },
+            err_fn,
+            None, // None = blocking timeout
+        )
+        .map_err(|e| e.to_string())
+}
+
+// Map any sample format to the -1.0..1.0 range
+fn samples_to_f32<T>(data: &[T]) -> impl Iterator<Item = f32> + '_
+where
+    T: Sample,
+    f32: FromSample<T>,
+{
+    data.iter().map(|sample| sample.to_sample::<f32>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_default_config;
+
+    #[test]
+    fn integer_formats_are_scaled_to_unit_range() {
+        let signed: Vec<f32> = samples_to_f32(&[i16::MIN, 0, i16::MAX / 2]).collect();
+        let unsigned: Vec<f32> = samples_to_f32(&[0u8, 128, 255]).collect();
+        let wide: Vec<f32> = samples_to_f32(&[i32::MIN, 0]).collect();
+
+        assert_eq!(signed[0], -1.0);
+        assert_eq!(signed[1], 0.0);
+        assert!((signed[2] - 0.5).abs() < 0.001);
+        assert_eq!(unsigned[0], -1.0);
+        assert_eq!(unsigned[1], 0.0);
+        assert!((unsigned[2] - 1.0).abs() < 0.01);
+        assert_eq!(wide, vec![-1.0, 0.0]);
+    }
+
+    #[test]
+    fn level_reports_rms_and_peak() {
+        let level = MicLevel::measure(&[0.5, -0.5, 0.5, -1.0]);
+
+        assert!((level.rms - 0.6614).abs() < 0.001);
+        assert_eq!(level.peak, 1.0);
+        assert_eq!(level.get_peak_db(), 0.0);
+        assert_eq!(MicLevel::measure(&[]), MicLevel::default());
+    }
+
+    #[test]
+    fn meter_follows_decibels() {
+        let meter = |rms| MicLevel { rms, peak: rms }.get_meter();
+
+        assert_eq!(meter(1.0), 1.0);
+        assert!((meter(0.031_622_8) - 0.5).abs() < 0.001);
+        assert_eq!(meter(0.001), 0.0);
+        assert_eq!(meter(0.0), 0.0);
+        assert_eq!(MicLevel::default().get_peak_db(), METER_FLOOR_DB);
+    }
+
+    #[test]
+    fn float_formats_are_kept() {
+        let single: Vec<f32> = samples_to_f32(&[0.25f32, -0.5]).collect();
+        let double: Vec<f32> = samples_to_f32(&[0.25f64, -0.5]).collect();
+        let packed: Vec<f32> = samples_to_f32(&[I24::new(-(1 << 23)).unwrap()]).collect();
+
+        assert_eq!(single, vec![0.25, -0.5]);
+        assert_eq!(double, vec![0.25, -0.5]);
+        assert_eq!(packed, vec![-1.0]);
+    }
+
+    #[test]
+    fn ring_buffer_keeps_the_latest_samples() {
+        let mut ring = RingBuffer::new(3);
+
+        ring.extend_samples([0.1, 0.2].into_iter());
+        assert_eq!(ring.to_vec(), vec![0.1, 0.2]);
+        ring.extend_samples([0.3, 0.4, 0.5].into_iter());
+        assert_eq!(ring.to_vec(), vec![0.3, 0.4, 0.5]);
+        ring.clear();
+        assert!(ring.to_vec().is_empty());
+    }
+
+    #[test]
+    fn restart_detection_returns_whole_frames() {
+        let mut recorder = AudioRecorder::new(&get_default_config());
+        recorder.config.channels = 2;
+        recorder.config.sample_rate = cpal::SampleRate(48000);
+        // One second of stereo and half a frame still arriving
+        *recorder.audio_buffer.lock().unwrap() = vec![0.0; 96001];
 
-        output
+        assert_eq!(recorder.restart_detection(), Duration::from_secs(1));
+        assert_eq!(recorder.vad_position, 96000);
+        assert_eq!(recorder.get_trailing_silence(), Duration::ZERO);
     }
 }
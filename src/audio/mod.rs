@@ -1,2 +1,10 @@
 pub mod micro;
+pub mod models;
+pub mod resample;
+pub mod segmenter;
+pub mod speaker;
 pub mod stt;
+#[cfg(test)]
+mod test_signals;
+pub mod vad;
+pub mod wake;
@@ -0,0 +1,152 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+// A ggml Whisper model found on disk
+#[derive(Debug, Clone, PartialEq)]
+pub struct SttModel {
+    pub name: String,
+    pub path: PathBuf,
+    // Size in bytes
+    pub size: u64,
+    // Models ending with .en only understand English
+    pub english_only: bool,
+}
+
+impl SttModel {
+    // Short description shown in the settings
+    pub fn describe(&self) -> String {
+        format!(
+            "{} ({} MB, {})",
+            self.name,
+            self.size / (1024 * 1024),
+            if self.english_only {
+                "English only"
+            } else {
+                "multilingual"
+            }
+        )
+    }
+}
+
+// Where models are looked for, in order: the user data dir then ./models
+pub fn get_models_folders() -> Vec<PathBuf> {
+    let mut folders = vec![];
+    if let Some(data_dir) = dirs::data_dir() {
+        folders.push(data_dir.join("potato_assistant").join("models"));
+    }
+    folders.push(PathBuf::from("models"));
+    folders
+}
+
+// Every ggml model of the folders, sorted by name
+pub fn list_models(folders: &[PathBuf]) -> Vec<SttModel> {
+    let mut models: Vec<SttModel> = folders
+        .iter()
+        .filter_map(|folder| fs::read_dir(folder).ok())
+        .flat_map(|entries| entries.flatten())
+        .filter_map(|entry| read_model(&entry.path()))
+        .collect();
+    models.sort_by(|a, b| a.name.cmp(&b.name));
+    models.dedup_by(|a, b| a.name == b.name);
+    models
+}
+
+fn read_model(path: &Path) -> Option<SttModel> {
+    let name = path.file_name()?.to_str()?.to_string();
+    if !name.starts_with("ggml-") || !name.ends_with(".bin") {
+        return None;
+    }
+    let metadata = fs::metadata(path).ok().filter(|m| m.is_file())?;
+    Some(SttModel {
+        english_only: name.trim_end_matches(".bin").ends_with(".en"),
+        name,
+        path: path.to_path_buf(),
+        size: metadata.len(),
+    })
+}
+
+// Accepts a path, a file name ("ggml-base.en.bin") or a short name ("base.en")
+pub fn find_model(stt_model: &str, folders: &[PathBuf]) -> Result<PathBuf, String> {
+    let stt_model = stt_model.trim();
+    if stt_model.is_empty() {
+        return Err("No Whisper model set, fill stt_model in the settings".to_string());
+    }
+    if Path::new(stt_model).is_file() {
+        return Ok(PathBuf::from(stt_model));
+    }
+
+    let file_name = if stt_model.ends_with(".bin") {
+        stt_model.to_string()
+    } else {
+        format!("ggml-{}.bin", stt_model)
+    };
+    folders
+        .iter()
+        .map(|folder| folder.join(&file_name))
+        .find(|path| path.is_file())
+        .ok_or_else(|| {
+            format!(
+                "Whisper model {} not found, download it into {}",
+                file_name,
+                folders
+                    .iter()
+                    .map(|folder| folder.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(" or ")
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_test_folder(name: &str) -> PathBuf {
+        let folder =
+            std::env::temp_dir().join(format!("potato_models_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    #[test]
+    fn lists_only_ggml_models() {
+        let folder = get_test_folder("list");
+        fs::write(folder.join("ggml-base.en.bin"), vec![0u8; 2 * 1024 * 1024]).unwrap();
+        fs::write(folder.join("ggml-small.bin"), "model").unwrap();
+        fs::write(folder.join("notes.txt"), "ignored").unwrap();
+
+        let models = list_models(&[folder.clone(), folder.join("missing")]);
+
+        assert_eq!(models.len(), 2);
+        assert_eq!(
+            models[0].describe(),
+            "ggml-base.en.bin (2 MB, English only)"
+        );
+        assert!(!models[1].english_only);
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn finds_models_by_short_name_or_reports_where_to_put_them() {
+        let folder = get_test_folder("find");
+        fs::write(folder.join("ggml-tiny.bin"), "model").unwrap();
+        let folders = vec![folder.join("first"), folder.clone()];
+
+        assert_eq!(
+            find_model("tiny", &folders),
+            Ok(folder.join("ggml-tiny.bin"))
+        );
+        assert_eq!(
+            find_model("ggml-tiny.bin", &folders),
+            Ok(folder.join("ggml-tiny.bin"))
+        );
+        let error = find_model("large", &folders).unwrap_err();
+        assert!(error.contains("ggml-large.bin"));
+        assert!(error.contains(&folder.join("first").display().to_string()));
+        assert!(find_model(" ", &folders).is_err());
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -0,0 +1,209 @@
+use std::f64::consts::PI;
+
+// Sample rate expected by Whisper
+pub const WHISPER_SAMPLE_RATE: u32 = 16000;
+
+// Zero crossings of the sinc kept on each side, more means a sharper filter
+const ZERO_CROSSINGS: f64 = 16.0;
+// Cutoff as a fraction of the output Nyquist, leaves room for the transition band
+const ROLLOFF: f64 = 0.9;
+// Kaiser window shape, about 85 dB of stop band attenuation
+const KAISER_BETA: f64 = 8.6;
+
+// Average the interleaved channels of every frame, an incomplete last frame is dropped
+pub fn downmix_to_mono(input_data: &[f32], channels: u16) -> Vec<f32> {
+    let channels = channels.max(1) as usize;
+    if channels == 1 {
+        return input_data.to_vec();
+    }
+    input_data
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect()
+}
+
+pub fn resample_to_16k(input_data: &[f32], input_rate: u32) -> Vec<f32> {
+    resample(input_data, input_rate, WHISPER_SAMPLE_RATE)
+}
+
+// Band limited resampling with a Kaiser windowed sinc.
+// The rates ratio is reduced to up / down so the filter is computed once per phase (polyphase).
+pub fn resample(input_data: &[f32], input_rate: u32, output_rate: u32) -> Vec<f32> {
+    if input_rate == output_rate || input_rate == 0 || output_rate == 0 || input_data.is_empty() {
+        return input_data.to_vec();
+    }
+
+    let divisor = gcd(input_rate, output_rate);
+    let up = (output_rate / divisor) as usize;
+    let down = (input_rate / divisor) as usize;
+
+    // Cutoff in cycles per input sample, below the Nyquist of the slowest rate
+    let cutoff = 0.5 * (output_rate as f64 / input_rate as f64).min(1.0) * ROLLOFF;
+    let half_width = (ZERO_CROSSINGS / (2.0 * cutoff)).ceil() as isize;
+    let phases: Vec<Vec<f32>> = (0..up)
+        .map(|phase| get_phase_weights(phase as f64 / up as f64, half_width, cutoff))
+        .collect();
+
+    let output_len = (input_data.len() as u64 * up as u64).div_ceil(down as u64) as usize;
+    let mut output = Vec::with_capacity(output_len);
+    for n in 0..output_len {
+        // Exact position of the output sample in the input: center + phase / up
+        let position = n * down;
+        let center = (position / up) as isize;
+        let weights = &phases[position % up];
+
+        let mut sample = 0.0;
+        for (weight, index) in weights.iter().zip(center - half_width + 1..) {
+            // The signal is considered silent outside of the recording
+            if index >= 0
+                && let Some(value) = input_data.get(index as usize)
+            {
+                sample += weight * value;
+            }
+        }
+        output.push(sample);
+    }
+    output
+}
+
+// Filter taps around an output sample sitting `offset` input samples after its center tap
+fn get_phase_weights(offset: f64, half_width: isize, cutoff: f64) -> Vec<f32> {
+    let weights: Vec<f64> = (-half_width + 1..=half_width)
+        .map(|tap| {
+            let x = tap as f64 - offset;
+            2.0 * cutoff * sinc(2.0 * cutoff * x) * kaiser(x / half_width as f64)
+        })
+        .collect();
+    // Unity gain for a constant signal whatever the phase
+    let sum: f64 = weights.iter().sum();
+    weights.iter().map(|w| (w / sum) as f32).collect()
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-12 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+// Kaiser window over [-1, 1]
+fn kaiser(x: f64) -> f64 {
+    if x.abs() > 1.0 {
+        return 0.0;
+    }
+    bessel_i0(KAISER_BETA * (1.0 - x * x).sqrt()) / bessel_i0(KAISER_BETA)
+}
+
+// Modified Bessel function of the first kind, order 0, from its power series
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half_x = x / 2.0;
+    for k in 1..50 {
+        term *= (half_x / k as f64).powi(2);
+        sum += term;
+        if term < sum * 1e-12 {
+            break;
+        }
+    }
+    sum
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_sine(frequency: f64, sample_rate: u32, seconds: f64) -> Vec<f32> {
+        (0..(sample_rate as f64 * seconds) as usize)
+            .map(|i| (2.0 * PI * frequency * i as f64 / sample_rate as f64).sin() as f32 * 0.5)
+            .collect()
+    }
+
+    // RMS away from the edges, where the filter only sees part of the signal
+    fn get_rms(samples: &[f32]) -> f32 {
+        let middle = &samples[samples.len() / 4..samples.len() * 3 / 4];
+        (middle.iter().map(|s| s * s).sum::<f32>() / middle.len() as f32).sqrt()
+    }
+
+    // Amplitude of one frequency (Goertzel), away from the edges
+    fn get_amplitude(samples: &[f32], frequency: f64, sample_rate: u32) -> f64 {
+        let middle = &samples[samples.len() / 4..samples.len() * 3 / 4];
+        let coefficient = 2.0 * (2.0 * PI * frequency / sample_rate as f64).cos();
+        let (mut previous, mut before_previous) = (0.0, 0.0);
+        for sample in middle {
+            let current = *sample as f64 + coefficient * previous - before_previous;
+            before_previous = previous;
+            previous = current;
+        }
+        let power = previous * previous + before_previous * before_previous
+            - coefficient * previous * before_previous;
+        2.0 * power.sqrt() / middle.len() as f64
+    }
+
+    fn to_db(ratio: f64) -> f64 {
+        20.0 * ratio.log10()
+    }
+
+    #[test]
+    fn downmix_averages_frames() {
+        let stereo = [1.0, 0.0, 0.5, 0.5, -1.0, 1.0, 0.25];
+
+        assert_eq!(downmix_to_mono(&stereo, 2), vec![0.5, 0.5, 0.0]);
+        assert_eq!(downmix_to_mono(&[0.1, 0.2], 1), vec![0.1, 0.2]);
+        assert_eq!(
+            downmix_to_mono(&[0.3, 0.3, 0.3, 0.6, 0.0, 0.0], 3),
+            vec![0.3, 0.2]
+        );
+    }
+
+    #[test]
+    fn keeps_speech_frequencies() {
+        for input_rate in [48000, 44100, 22050, 8000] {
+            let input = get_sine(1000.0, input_rate, 1.0);
+
+            let output = resample_to_16k(&input, input_rate);
+
+            let expected_len = input.len() * 16000 / input_rate as usize;
+            assert!(output.len().abs_diff(expected_len) <= 1);
+            let gain = get_amplitude(&output, 1000.0, WHISPER_SAMPLE_RATE) / 0.5;
+            assert!(
+                to_db(gain).abs() < 0.1,
+                "{} Hz: gain {} dB",
+                input_rate,
+                to_db(gain)
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_frequencies_above_the_new_nyquist() {
+        // Naive sample skipping folds these tones back at full amplitude
+        for (input_rate, frequency) in [(48000, 10000.0), (44100, 9000.0), (48000, 15000.0)] {
+            let input = get_sine(frequency, input_rate, 1.0);
+
+            let output = resample_to_16k(&input, input_rate);
+
+            let aliasing = to_db(get_rms(&output) as f64 / get_rms(&input) as f64);
+            assert!(
+                aliasing < -60.0,
+                "{} Hz tone at {} Hz aliased at {} dB",
+                frequency,
+                input_rate,
+                aliasing
+            );
+        }
+    }
+
+    #[test]
+    fn same_rate_is_untouched() {
+        let input = get_sine(440.0, 16000, 0.1);
+
+        assert_eq!(resample_to_16k(&input, 16000), input);
+        assert!(resample_to_16k(&[], 48000).is_empty());
+    }
+}
@@ -0,0 +1,250 @@
+// Words whose trailing dot does not end the sentence, lowercase
+const ABBREVIATIONS: [&str; 16] = [
+    "mr", "mrs", "ms", "dr", "prof", "sr", "jr", "vs", "etc", "e.g", "i.e", "eg", "ie", "fig",
+    "approx", "cf",
+];
+// Abbreviations that are also common words, only when capitalized ("St. Louis", "Acme Co.")
+const CAPITALIZED_ABBREVIATIONS: [&str; 4] = ["St", "Co", "Inc", "Ltd"];
+
+// Cuts a streamed answer into sentences that can be spoken as soon as they are complete
+#[derive(Debug, Default, Clone)]
+pub struct SentenceSegmenter {
+    // Received text not spoken yet
+    pending: String,
+    // Language of the code block being received, Some while inside one
+    code_block: Option<String>,
+}
+
+impl SentenceSegmenter {
+    // Sentences of a whole message
+    pub fn split(text: &str) -> Vec<String> {
+        let mut segmenter = Self::default();
+        let mut sentences = segmenter.push(text);
+        sentences.extend(segmenter.finish());
+        sentences
+    }
+
+    // Add a chunk of the answer and return the sentences it completed
+    pub fn push(&mut self, delta: &str) -> Vec<String> {
+        self.pending.push_str(delta);
+        let mut sentences = vec![];
+        loop {
+            if self.code_block.is_some() {
+                // Code is not read aloud, only mentioned once it is complete
+                let Some(fence) = self.pending.find("```") else {
+                    break;
+                };
+                self.pending.drain(..fence + 3);
+                sentences.extend(self.code_block.take().map(|lang| describe_code(&lang)));
+                continue;
+            }
+
+            let fence = self.pending.find("```");
+            let text = &self.pending[..fence.unwrap_or(self.pending.len())];
+            if let Some(end) = find_sentence_end(text) {
+                let sentence: String = self.pending.drain(..end).collect();
+                sentences.extend(get_speakable(&sentence));
+                continue;
+            }
+            // The language is only known once the opening fence line is complete
+            let Some(line_end) = fence.and_then(|f| self.pending[f..].find('\n').map(|n| f + n))
+            else {
+                break;
+            };
+            let fence = fence.unwrap_or_default();
+            sentences.extend(get_speakable(&self.pending[..fence]));
+            self.code_block = Some(self.pending[fence + 3..line_end].trim().to_string());
+            self.pending.drain(..=line_end);
+        }
+        sentences
+    }
+
+    // The answer is complete, return what is left
+    pub fn finish(&mut self) -> Vec<String> {
+        let sentences = match self.code_block.take() {
+            Some(lang) => vec![describe_code(&lang)],
+            None => get_speakable(&self.pending).into_iter().collect(),
+        };
+        self.pending.clear();
+        sentences
+    }
+}
+
+// Byte index right after the first complete sentence, None while it may still go on
+fn find_sentence_end(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        let end = index + c.len_utf8();
+        if c == '\n' {
+            return Some(end);
+        }
+        // Only a following space tells that the sentence is over, "3.14" is a number
+        let followed_by_space = chars.peek().is_some_and(|(_, next)| next.is_whitespace());
+        match c {
+            '!' | '?' | '…' if followed_by_space => return Some(end),
+            '.' if followed_by_space && !is_abbreviation(&text[..index], &text[end..]) => {
+                return Some(end);
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Whether the word before a dot is an abbreviation, an initial or a list number
+fn is_abbreviation(before_dot: &str, after_dot: &str) -> bool {
+    let line = before_dot.rsplit('\n').next().unwrap_or_default();
+    let mut words = line.rsplit(char::is_whitespace);
+    let word = words.next().unwrap_or_default();
+    let word = word.trim_start_matches(['(', '"', '\'']);
+    let is_initial = word.chars().count() == 1 && word.chars().all(char::is_uppercase);
+    let is_list_number =
+        !word.is_empty() && word.chars().all(|c| c.is_ascii_digit()) && line.trim_start() == word;
+    let is_et_al = word == "al" && words.next() == Some("et");
+    // "No. 5", wait for what follows while only the space has arrived
+    let next = after_dot.trim_start();
+    let is_number_sign = word == "No" && next.chars().next().is_none_or(|c| c.is_ascii_digit());
+    is_initial
+        || is_list_number
+        || is_et_al
+        || is_number_sign
+        || CAPITALIZED_ABBREVIATIONS.contains(&word)
+        || ABBREVIATIONS.contains(&word.to_lowercase().as_str())
+}
+
+fn describe_code(lang: &str) -> String {
+    if lang.is_empty() {
+        "There is a code example in the chat.".to_string()
+    } else {
+        format!("There is a {} code example in the chat.", lang)
+    }
+}
+
+// Remove the markdown so it is not read aloud, None when nothing is left to say
+fn get_speakable(sentence: &str) -> Option<String> {
+    let sentence = sentence.trim().trim_start_matches('#').trim_start();
+    let sentence = ["- ", "* ", "+ ", "> "]
+        .iter()
+        .find_map(|marker| sentence.strip_prefix(marker))
+        .unwrap_or(sentence);
+
+    let mut speakable = String::new();
+    let mut rest = sentence;
+    // Links are read as their text only
+    while let Some(start) = rest.find('[') {
+        let Some((label, url_and_rest)) = rest[start + 1..].split_once("](") else {
+            break;
+        };
+        let Some(url_end) = url_and_rest.find(')') else {
+            break;
+        };
+        speakable.push_str(&rest[..start]);
+        speakable.push_str(label);
+        rest = &url_and_rest[url_end + 1..];
+    }
+    speakable.push_str(rest);
+    speakable.retain(|c| c != '*' && c != '`');
+
+    let speakable = speakable.trim().to_string();
+    speakable
+        .chars()
+        .any(char::is_alphanumeric)
+        .then_some(speakable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sentences_come_out_as_soon_as_they_end() {
+        let mut segmenter = SentenceSegmenter::default();
+
+        assert!(segmenter.push("Hello there").is_empty());
+        // The dot may still be part of a number
+        assert_eq!(segmenter.push("! It costs 3."), vec!["Hello there!"]);
+        assert_eq!(
+            segmenter.push("14 euros. Really"),
+            vec!["It costs 3.14 euros."]
+        );
+        assert_eq!(segmenter.finish(), vec!["Really"]);
+    }
+
+    #[test]
+    fn abbreviations_and_list_numbers_do_not_end_sentences() {
+        assert_eq!(
+            SentenceSegmenter::split(
+                "Dr. Smith met J. R. Tolkien, e.g. in a pub. Steps:\n1. Open it\n2. Close it"
+            ),
+            vec![
+                "Dr. Smith met J. R. Tolkien, e.g. in a pub.",
+                "Steps:",
+                "1. Open it",
+                "2. Close it"
+            ]
+        );
+        assert_eq!(
+            SentenceSegmenter::split(
+                "See No. 5 near St. Louis, Smith et al. agree. No. Not today."
+            ),
+            vec![
+                "See No. 5 near St. Louis, Smith et al. agree.",
+                "No.",
+                "Not today."
+            ]
+        );
+        // Common words ending a sentence
+        assert_eq!(
+            SentenceSegmenter::split(
+                "The answer is no. Then stop. Set it to max. Done. At least min. Ok"
+            ),
+            vec![
+                "The answer is no.",
+                "Then stop.",
+                "Set it to max.",
+                "Done.",
+                "At least min.",
+                "Ok"
+            ]
+        );
+    }
+
+    #[test]
+    fn code_blocks_are_summarized() {
+        let mut segmenter = SentenceSegmenter::default();
+        let mut sentences = vec![];
+        // Fences split across chunks like the providers do
+        for delta in [
+            "Try this:\n``",
+            "`rust\nfn main() {",
+            " println!(\"Hi. There\"); }\n`",
+            "``\nDone",
+        ] {
+            sentences.extend(segmenter.push(delta));
+        }
+        sentences.extend(segmenter.finish());
+
+        assert_eq!(
+            sentences,
+            vec![
+                "Try this:",
+                "There is a rust code example in the chat.",
+                "Done"
+            ]
+        );
+        assert_eq!(
+            SentenceSegmenter::split("```\nunfinished"),
+            vec!["There is a code example in the chat."]
+        );
+    }
+
+    #[test]
+    fn markdown_is_not_read() {
+        assert_eq!(
+            SentenceSegmenter::split(
+                "## Title\n- **Bold** `code` and [a link](https://example.com).\n---\n"
+            ),
+            vec!["Title", "Bold code and a link."]
+        );
+    }
+}
@@ -0,0 +1,339 @@
+use std::{
+    collections::VecDeque,
+    fmt::Debug,
+    fs,
+    io::{Cursor, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::Arc,
+};
+
+use rodio::{OutputStream, OutputStreamBuilder, Sink, buffer::SamplesBuffer};
+
+use crate::config::{AppConfig, TtsEngine};
+
+// Piper voices are trained at this rate unless their .onnx.json says otherwise
+const PIPER_DEFAULT_SAMPLE_RATE: u32 = 22050;
+
+// Synthesized audio, ready to be played
+#[derive(Debug, Clone, PartialEq)]
+pub struct Speech {
+    // Interleaved samples between -1 and 1
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+// Turns text into audio, runs on a background thread
+pub trait SpeechEngine: Send + Sync + Debug {
+    fn synthesize(&self, text: &str) -> Result<Speech, String>;
+}
+
+// espeak-ng, robotic but installed almost everywhere
+#[derive(Debug)]
+pub struct EspeakEngine {
+    // Voice name as listed by `espeak-ng --voices`, e.g. "en" or "fr"
+    pub voice: String,
+}
+
+impl SpeechEngine for EspeakEngine {
+    fn synthesize(&self, text: &str) -> Result<Speech, String> {
+        let mut command = Command::new("espeak-ng");
+        command.args(["--stdout", "--stdin"]);
+        if !self.voice.trim().is_empty() {
+            command.args(["-v", self.voice.trim()]);
+        }
+        let output = run_engine(command, text).map_err(|e| format!("espeak-ng: {}", e))?;
+        read_wav(&output)
+    }
+}
+
+// Piper, natural neural voices running locally
+#[derive(Debug)]
+pub struct PiperEngine {
+    // The .onnx voice, its .onnx.json config is expected next to it
+    pub model: PathBuf,
+}
+
+impl SpeechEngine for PiperEngine {
+    fn synthesize(&self, text: &str) -> Result<Speech, String> {
+        let mut command = Command::new("piper");
+        command.arg("--model").arg(&self.model).arg("--output_raw");
+        let output = run_engine(command, text).map_err(|e| format!("piper: {}", e))?;
+        Ok(Speech {
+            samples: read_pcm_16(&output),
+            sample_rate: get_piper_sample_rate(&self.model),
+            channels: 1,
+        })
+    }
+}
+
+pub fn get_engine(config: &AppConfig) -> Result<Arc<dyn SpeechEngine>, String> {
+    match config.tts_engine {
+        TtsEngine::Espeak => Ok(Arc::new(EspeakEngine {
+            voice: config.tts_voice.clone(),
+        })),
+        TtsEngine::Piper => {
+            if config.tts_voice.trim().is_empty() {
+                return Err("No Piper voice set, fill tts_voice with a .onnx path".to_string());
+            }
+            Ok(Arc::new(PiperEngine {
+                model: PathBuf::from(config.tts_voice.trim()),
+            }))
+        }
+    }
+}
+
+// Feed the text on stdin and return what the engine wrote on stdout
+fn run_engine(mut command: Command, text: &str) -> Result<Vec<u8>, String> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("cannot start, is it installed? {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .map_err(|e| e.to_string())?;
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(output.stdout)
+}
+
+// Engines writing to a pipe cannot fix the WAV header, so the data length is not trusted
+fn read_wav(bytes: &[u8]) -> Result<Speech, String> {
+    let reader = hound::WavReader::new(Cursor::new(bytes))
+        .map_err(|e| format!("Invalid audio from the speech engine: {}", e))?;
+    let spec = reader.spec();
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Float => reader.into_samples::<f32>().map_while(Result::ok).collect(),
+        hound::SampleFormat::Int => {
+            let scale = (1u32 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .into_samples::<i32>()
+                .map_while(Result::ok)
+                .map(|s| s as f32 / scale)
+                .collect()
+        }
+    };
+    Ok(Speech {
+        samples,
+        sample_rate: spec.sample_rate,
+        channels: spec.channels,
+    })
+}
+
+// Raw signed 16 bit little endian samples, a trailing odd byte is dropped
+fn read_pcm_16(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(2)
+        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]) as f32 / 32768.0)
+        .collect()
+}
+
+fn get_piper_sample_rate(model: &Path) -> u32 {
+    let mut config_path = model.as_os_str().to_owned();
+    config_path.push(".json");
+    fs::read_to_string(config_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|config| config["audio"]["sample_rate"].as_u64())
+        .map(|rate| rate as u32)
+        .unwrap_or(PIPER_DEFAULT_SAMPLE_RATE)
+}
+
+// AppConfig::volume goes from 0 to 100
+pub fn get_gain(volume: u8) -> f32 {
+    volume.min(100) as f32 / 100.0
+}
+
+// Plays the synthesized messages one after the other
+pub struct Speaker {
+    engine: Result<Arc<dyn SpeechEngine>, String>,
+    // Opened on the first message so a missing output device does not block the app
+    output: Option<(OutputStream, Sink)>,
+    gain: f32,
+    // Texts waiting to be synthesized, in speaking order
+    queue: VecDeque<String>,
+    pub synthesizing: bool,
+}
+
+impl Debug for Speaker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Speaker")
+            .field("engine", &self.engine)
+            .field("gain", &self.gain)
+            .field("queue", &self.queue)
+            .field("synthesizing", &self.synthesizing)
+            .finish()
+    }
+}
+
+impl Speaker {
+    pub fn new(config: &AppConfig) -> Self {
+        Self {
+            engine: get_engine(config),
+            output: None,
+            gain: get_gain(config.volume),
+            queue: VecDeque::new(),
+            synthesizing: false,
+        }
+    }
+
+    pub fn set_config(&mut self, config: &AppConfig) {
+        self.engine = get_engine(config);
+        self.gain = get_gain(config.volume);
+        if let Some((_, sink)) = &self.output {
+            sink.set_volume(self.gain);
+        }
+    }
+
+    pub fn get_engine(&self) -> Result<Arc<dyn SpeechEngine>, String> {
+        self.engine.clone()
+    }
+
+    pub fn speak(&mut self, text: &str) {
+        if !text.trim().is_empty() {
+            self.queue.push_back(text.trim().to_string());
+        }
+    }
+
+    // Next text to synthesize, one at a time so the messages keep their order
+    pub fn take_next(&mut self) -> Option<String> {
+        if self.synthesizing {
+            return None;
+        }
+        let text = self.queue.pop_front()?;
+        self.synthesizing = true;
+        Some(text)
+    }
+
+    // Queue the audio after what is already playing
+    pub fn play(&mut self, speech: Speech) -> Result<(), String> {
+        if self.output.is_none() {
+            let mut stream = OutputStreamBuilder::open_default_stream()
+                .map_err(|e| format!("No audio output: {}", e))?;
+            stream.log_on_drop(false);
+            let sink = Sink::connect_new(stream.mixer());
+            sink.set_volume(self.gain);
+            self.output = Some((stream, sink));
+        }
+        if let Some((_, sink)) = &self.output {
+            sink.append(SamplesBuffer::new(
+                speech.channels.max(1),
+                speech.sample_rate,
+                speech.samples,
+            ));
+        }
+        Ok(())
+    }
+
+    // Silence now and forget everything queued
+    pub fn stop(&mut self) {
+        self.queue.clear();
+        self.synthesizing = false;
+        if let Some((stream, sink)) = &mut self.output {
+            // A dropped sink stops its sounds, a new one takes the next messages
+            *sink = Sink::connect_new(stream.mixer());
+            sink.set_volume(self.gain);
+        }
+    }
+
+    pub fn is_speaking(&self) -> bool {
+        self.synthesizing
+            || !self.queue.is_empty()
+            || self.output.as_ref().is_some_and(|(_, sink)| !sink.empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_default_config;
+
+    fn get_wav(samples: &[i16], sample_rate: u32) -> Vec<u8> {
+        let mut bytes = Cursor::new(vec![]);
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::new(&mut bytes, spec).unwrap();
+        for sample in samples {
+            writer.write_sample(*sample).unwrap();
+        }
+        writer.finalize().unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn reads_wav_with_an_unfinished_header() {
+        let mut wav = get_wav(&[0, 16384, -32768], 22050);
+        // What espeak-ng writes on stdout: a placeholder data length
+        let data_len = wav.len() - 4 - 6;
+        wav[data_len..data_len + 4].copy_from_slice(&0x7fff_f000u32.to_le_bytes());
+
+        let speech = read_wav(&wav).unwrap();
+
+        assert_eq!(speech.samples, vec![0.0, 0.5, -1.0]);
+        assert_eq!(speech.sample_rate, 22050);
+        assert_eq!(speech.channels, 1);
+        assert!(read_wav(b"not a wav").is_err());
+    }
+
+    #[test]
+    fn reads_raw_pcm() {
+        let bytes = [0x00, 0x40, 0x00, 0x80, 0x01];
+
+        assert_eq!(read_pcm_16(&bytes), vec![0.5, -1.0]);
+    }
+
+    #[test]
+    fn piper_sample_rate_comes_from_the_voice_config() {
+        let folder = std::env::temp_dir().join(format!("potato_piper_{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let model = folder.join("voice.onnx");
+        fs::write(
+            folder.join("voice.onnx.json"),
+            "{\"audio\": {\"sample_rate\": 16000}}",
+        )
+        .unwrap();
+
+        assert_eq!(get_piper_sample_rate(&model), 16000);
+        assert_eq!(
+            get_piper_sample_rate(&folder.join("missing.onnx")),
+            PIPER_DEFAULT_SAMPLE_RATE
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn queue_keeps_order_and_stop_clears_it() {
+        let mut speaker = Speaker::new(&get_default_config());
+        speaker.speak("First");
+        speaker.speak("  ");
+        speaker.speak("Second");
+
+        assert_eq!(speaker.take_next(), Some("First".to_string()));
+        // Still synthesizing the first one
+        assert_eq!(speaker.take_next(), None);
+        speaker.synthesizing = false;
+        assert_eq!(speaker.take_next(), Some("Second".to_string()));
+        speaker.speak("Third");
+        speaker.stop();
+        assert!(!speaker.is_speaking());
+        assert_eq!(speaker.take_next(), None);
+    }
+
+    #[test]
+    fn volume_is_a_percentage() {
+        assert_eq!(get_gain(50), 0.5);
+        assert_eq!(get_gain(0), 0.0);
+        assert_eq!(get_gain(255), 1.0);
+    }
+}
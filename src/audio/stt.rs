@@ -1,9 +1,161 @@
+use std::{fmt, time::Duration};
+
 use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
 
+use crate::{
+    audio::models::{find_model, get_models_folders},
+    config::{AppConfig, SttSampling},
+};
+
+// Language value asking Whisper to detect the spoken language
+pub const AUTO_LANGUAGE: &str = "auto";
+// Live transcription window longer than this gets its oldest segments committed
+const MAX_LIVE_WINDOW: Duration = Duration::from_secs(10);
+// Kept before an interruption so its first syllable is transcribed
+const BARGE_IN_PRE_ROLL: Duration = Duration::from_millis(300);
+
+// One piece of speech as cut by Whisper
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptSegment {
+    pub text: String,
+    pub start: Duration,
+    pub end: Duration,
+    // Mean probability of the text tokens, between 0 and 1
+    pub confidence: f32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Transcript {
+    pub segments: Vec<TranscriptSegment>,
+    // Language code the audio was transcribed as, detected when asked for "auto"
+    pub language: Option<String>,
+}
+
+impl Transcript {
+    // Whole transcription as a single line of text
+    pub fn text(&self) -> String {
+        self.segments
+            .iter()
+            .map(|segment| segment.text.trim())
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+// Text transcribed while still recording. The audio before `committed_until` is final and never
+// transcribed again, only the window after it is, over and over as the recording grows.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LiveTranscript {
+    pub committed: String,
+    pub committed_until: Duration,
+    // Latest guess for the window, may still change
+    pub partial: String,
+}
+
+impl LiveTranscript {
+    // What was recorded before the user interrupted the assistant is its own voice
+    pub fn after_barge_in(recorded: Duration) -> Self {
+        Self {
+            committed_until: recorded.saturating_sub(BARGE_IN_PRE_ROLL),
+            ..Self::default()
+        }
+    }
+
+    pub fn text(&self) -> String {
+        join_text(&self.committed, &self.partial)
+    }
+
+    // Take the transcription of the current window, `window` long
+    pub fn update(&mut self, transcript: &Transcript, window: Duration) {
+        self.partial = transcript.text();
+        if window < MAX_LIVE_WINDOW {
+            return;
+        }
+        // The last segment may end mid word, it stays in the window
+        let Some((last, done)) = transcript.segments.split_last() else {
+            return;
+        };
+        if done.is_empty() {
+            return;
+        }
+        let done = Transcript {
+            segments: done.to_vec(),
+            language: None,
+        };
+        self.committed = join_text(&self.committed, &done.text());
+        self.committed_until += last.start;
+        self.partial = last.text.trim().to_string();
+    }
+
+    // Whole text once the last window is transcribed
+    pub fn finish(&self, transcript: &Transcript) -> String {
+        join_text(&self.committed, &transcript.text())
+    }
+}
+
+// Join two pieces of text with a space, ignoring empty ones
+pub fn join_text(first: &str, second: &str) -> String {
+    match (first.trim().is_empty(), second.trim().is_empty()) {
+        (true, _) => second.trim().to_string(),
+        (false, true) => first.to_string(),
+        (false, false) => format!("{} {}", first.trim_end(), second.trim()),
+    }
+}
+
+// Whisper code of a language setting, a locale such as "fr_FR.UTF-8" or "en_US:en" gives "fr" or "en"
+fn get_language_code(language: &str) -> String {
+    let language = language.trim().to_lowercase();
+    match language.split(['_', ':', '.', '-', '@']).next() {
+        Some(code) if !code.is_empty() => code.to_string(),
+        _ => AUTO_LANGUAGE.to_string(),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SttOptions {
+    pub language: String,
+    pub threads: u32,
+    pub sampling: SttSampling,
+    pub beam_size: u32,
+}
+
+impl SttOptions {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            language: get_language_code(&config.language),
+            threads: config.stt_threads.max(1),
+            sampling: config.stt_sampling.clone(),
+            beam_size: config.stt_beam_size.max(1),
+        }
+    }
+
+    pub fn is_auto(&self) -> bool {
+        self.language.is_empty() || self.language == AUTO_LANGUAGE
+    }
+
+    fn get_strategy(&self) -> SamplingStrategy {
+        match self.sampling {
+            SttSampling::Greedy => SamplingStrategy::Greedy { best_of: 1 },
+            SttSampling::BeamSearch => SamplingStrategy::BeamSearch {
+                beam_size: self.beam_size as i32,
+                // Default of whisper.cpp, unused by the current implementation
+                patience: -1.0,
+            },
+        }
+    }
+}
+
 pub struct LocalTranscriber {
     ctx: WhisperContext,
 }
 
+impl fmt::Debug for LocalTranscriber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalTranscriber").finish_non_exhaustive()
+    }
+}
+
 impl LocalTranscriber {
     pub fn new(model_path: &str) -> Result<Self, String> {
         // 1. Charger le modèle depuis le disque
@@ -13,14 +165,33 @@ impl LocalTranscriber {
         Ok(Self { ctx })
     }
 
-    pub fn transcribe(&mut self, audio_data: &[f32]) -> Result<String, String> {
+    // Load the model named by stt_model from the models folders
+    pub fn from_config(config: &AppConfig) -> Result<Self, String> {
+        let path = find_model(&config.stt_model, &get_models_folders())?;
+        Self::new(&path.to_string_lossy())
+    }
+
+    pub fn transcribe(
+        &self,
+        audio_data: &[f32],
+        options: &SttOptions,
+    ) -> Result<Transcript, String> {
         // 2. Configurer les paramètres de reconnaissance
-        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        let mut params = FullParams::new(options.get_strategy());
 
-        // Optimisations pour la vitesse
-        params.set_n_threads(4); // Utilise 4 cœurs CPU
+        params.set_n_threads(options.threads as i32);
         params.set_translate(false);
-        params.set_language(Some("en")); // Force l'anglais pour aller plus vite (ou "fr")
+        if options.is_auto() {
+            params.set_language(Some(AUTO_LANGUAGE));
+        } else if whisper_rs::get_lang_id(&options.language).is_some() {
+            params.set_language(Some(&options.language));
+        } else {
+            println!(
+                "Unknown Whisper language {}, detecting it instead",
+                options.language
+            );
+            params.set_language(Some(AUTO_LANGUAGE));
+        }
         params.set_print_special(false);
         params.set_print_progress(false);
         params.set_print_realtime(false);
@@ -34,17 +205,224 @@ impl LocalTranscriber {
             .full(params, audio_data)
             .map_err(|e| format!("Erreur pendant la transcription : {}", e))?;
 
-        // 4. Récupérer le texte
-        let num_segments = state.full_n_segments();
-        let mut text = String::new();
+        // 4. Récupérer les segments
+        // Token ids from end of text onwards are timestamps and control tokens
+        let token_eot = self.ctx.token_eot();
+        let segments = state
+            .as_iter()
+            .map(|segment| {
+                let text = segment
+                    .to_str_lossy()
+                    .map_err(|e| format!("Segment illisible : {}", e))?
+                    .trim()
+                    .to_string();
+                let probabilities: Vec<f32> = (0..segment.n_tokens())
+                    .filter_map(|i| segment.get_token(i))
+                    .filter(|token| token.token_id() < token_eot)
+                    .map(|token| token.token_probability())
+                    .collect();
+                Ok(TranscriptSegment {
+                    text,
+                    start: get_duration(segment.start_timestamp()),
+                    end: get_duration(segment.end_timestamp()),
+                    confidence: get_mean(&probabilities),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let language = if options.is_auto() {
+            whisper_rs::get_lang_str(state.full_lang_id_from_state()).map(str::to_string)
+        } else {
+            Some(options.language.clone())
+        };
+
+        Ok(Transcript { segments, language })
+    }
+}
+
+// Whisper timestamps are in centiseconds
+fn get_duration(centiseconds: i64) -> Duration {
+    Duration::from_millis(centiseconds.max(0) as u64 * 10)
+}
+
+fn get_mean(values: &[f32]) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f32>() / values.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::vad::VoiceDetector;
+
+    // A short spoken "hello"
+    const FIXTURE_WAV: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/hello_16k.wav");
+    const TEST_MODEL: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/models/ggml-base.en.bin");
+
+    fn get_segment(text: &str, start_ms: u64, end_ms: u64) -> TranscriptSegment {
+        TranscriptSegment {
+            text: text.to_string(),
+            start: Duration::from_millis(start_ms),
+            end: Duration::from_millis(end_ms),
+            confidence: 0.9,
+        }
+    }
+
+    fn read_fixture() -> Vec<f32> {
+        let mut reader = hound::WavReader::open(FIXTURE_WAV).unwrap();
+        assert_eq!(reader.spec().sample_rate, 16000);
+        assert_eq!(reader.spec().channels, 1);
+        reader
+            .samples::<i16>()
+            .map(|sample| sample.unwrap() as f32 / i16::MAX as f32)
+            .collect()
+    }
+
+    #[test]
+    fn text_joins_trimmed_segments() {
+        let transcript = Transcript {
+            segments: vec![
+                get_segment(" Hello", 0, 800),
+                get_segment("  ", 800, 900),
+                get_segment(" potato. ", 900, 1500),
+            ],
+            language: Some("en".to_string()),
+        };
+
+        assert_eq!(transcript.text(), "Hello potato.");
+        assert_eq!(Transcript::default().text(), "");
+    }
+
+    #[test]
+    fn timestamps_and_confidence_are_converted() {
+        assert_eq!(get_duration(150), Duration::from_millis(1500));
+        assert_eq!(get_duration(-1), Duration::ZERO);
+        assert_eq!(get_mean(&[]), 0.0);
+        assert!((get_mean(&[0.5, 1.0]) - 0.75).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn options_follow_app_config() {
+        let config = AppConfig {
+            language: " Auto ".to_string(),
+            stt_threads: 0,
+            stt_sampling: SttSampling::BeamSearch,
+            stt_beam_size: 3,
+            ..crate::config::get_default_config()
+        };
+
+        let options = SttOptions::from_config(&config);
+
+        assert!(options.is_auto());
+        assert_eq!(options.threads, 1);
+        assert!(matches!(
+            options.get_strategy(),
+            SamplingStrategy::BeamSearch { beam_size: 3, .. }
+        ));
+        assert!(!SttOptions::from_config(&crate::config::get_default_config()).is_auto());
+    }
+
+    #[test]
+    fn locales_give_the_language_code() {
+        assert_eq!(get_language_code("en_US:en"), "en");
+        assert_eq!(get_language_code(" fr_FR.UTF-8"), "fr");
+        assert_eq!(get_language_code("de"), "de");
+        assert_eq!(get_language_code(""), AUTO_LANGUAGE);
+        assert_eq!(get_language_code(":en"), AUTO_LANGUAGE);
+    }
+
+    #[test]
+    fn barge_in_keeps_a_pre_roll() {
+        let live = LiveTranscript::after_barge_in(Duration::from_secs(5));
+
+        assert_eq!(live.committed_until, Duration::from_millis(4700));
+        assert_eq!(live.text(), "");
+        // Interrupted right away
+        assert_eq!(
+            LiveTranscript::after_barge_in(Duration::from_millis(100)).committed_until,
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn live_transcript_commits_old_segments_of_long_windows() {
+        let mut live = LiveTranscript::default();
+        let short = Transcript {
+            segments: vec![
+                get_segment(" Hello", 0, 2000),
+                get_segment(" pot", 2000, 3000),
+            ],
+            language: None,
+        };
+        live.update(&short, Duration::from_secs(3));
+        assert_eq!(live.text(), "Hello pot");
+        assert_eq!(live.committed_until, Duration::ZERO);
+
+        let long = Transcript {
+            segments: vec![
+                get_segment(" Hello", 0, 2000),
+                get_segment(" potato,", 2000, 8000),
+                get_segment(" how are", 8000, 11000),
+            ],
+            language: None,
+        };
+        live.update(&long, Duration::from_secs(11));
+        assert_eq!(live.committed, "Hello potato,");
+        assert_eq!(live.committed_until, Duration::from_secs(8));
+        assert_eq!(live.text(), "Hello potato, how are");
+
+        let last_window = Transcript {
+            segments: vec![get_segment(" how are you?", 0, 2000)],
+            language: None,
+        };
+        assert_eq!(live.finish(&last_window), "Hello potato, how are you?");
+    }
+
+    #[test]
+    fn join_text_skips_empty_parts() {
+        assert_eq!(join_text("", " Hi"), "Hi");
+        assert_eq!(join_text("Hi", "  "), "Hi");
+        assert_eq!(join_text("Hi ", "potato"), "Hi potato");
+    }
+
+    #[test]
+    fn fixture_holds_speech_between_silences() {
+        let samples = read_fixture();
+        let mut vad = VoiceDetector::new(16000, 0.01);
+        vad.push(&samples);
+
+        assert!(vad.speech_started());
+        assert!(vad.speech_start().is_some_and(|start| start > 0));
+        assert!(vad.trailing_silence() > Duration::ZERO);
+    }
+
+    #[test]
+    fn transcribes_fixture_wav_into_segments() {
+        if !std::path::Path::new(TEST_MODEL).exists() {
+            println!("Skipped, no Whisper model at {}", TEST_MODEL);
+            return;
+        }
+        let samples = read_fixture();
+        let transcriber = LocalTranscriber::new(TEST_MODEL).unwrap();
+
+        let options = SttOptions {
+            language: AUTO_LANGUAGE.to_string(),
+            ..SttOptions::from_config(&crate::config::get_default_config())
+        };
+
+        let transcript = transcriber.transcribe(&samples, &options).unwrap();
 
-        // for i in 0..num_segments {
-        //     if let Ok(segment) = state.full_get_segment_text(i) {
-        //         text.push_str(&segment);
-        //         text.push(' ');
-        //     }
-        // }
+        assert!(transcript.language.is_some());
+        assert!(!transcript.segments.is_empty());
+        assert!(!transcript.text().is_empty());
 
-        Ok(text.trim().to_string())
+        let audio_length = Duration::from_secs_f32(samples.len() as f32 / 16000.0);
+        for segment in &transcript.segments {
+            assert!(segment.start <= segment.end);
+            assert!(segment.end <= audio_length + Duration::from_millis(500));
+            assert!((0.0..=1.0).contains(&segment.confidence));
+        }
     }
 }
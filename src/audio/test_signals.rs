@@ -0,0 +1,15 @@
+// Sounds at 16 kHz used by the audio tests instead of a microphone
+use std::f32::consts::PI;
+
+pub const SIGNAL_RATE: usize = 16000;
+
+pub fn get_silence(ms: usize) -> Vec<f32> {
+    vec![0.0; SIGNAL_RATE * ms / 1000]
+}
+
+// Voiced sound: a low pitch tone well above the detection threshold
+pub fn get_voice(ms: usize) -> Vec<f32> {
+    (0..SIGNAL_RATE * ms / 1000)
+        .map(|i| (2.0 * PI * 200.0 * i as f32 / SIGNAL_RATE as f32).sin() * 0.3)
+        .collect()
+}
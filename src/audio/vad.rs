@@ -0,0 +1,201 @@
+use std::time::Duration;
+
+// Length of the analysed frames, short enough to follow syllables
+const FRAME_DURATION_MS: u32 = 20;
+// Frames whose signal crosses zero more often than this are hiss, not voice
+const MAX_ZERO_CROSSING_RATE: f32 = 0.35;
+// Speech must be this many times louder than the background noise
+const NOISE_FLOOR_RATIO: f32 = 3.0;
+// Kept before the first speech frame so the first syllable is not cut
+const PRE_ROLL: Duration = Duration::from_millis(200);
+// Silence ending a turn in conversation mode when vad_silence_ms does not stop recordings
+const CONVERSATION_SILENCE: Duration = Duration::from_millis(1500);
+
+// Energy and zero crossing voice activity detector, fed with mono samples
+#[derive(Debug, Clone)]
+pub struct VoiceDetector {
+    frame_len: usize,
+    sample_rate: u32,
+    // Minimum RMS of a speech frame
+    threshold: f32,
+    // Slow average of the RMS of the non speech frames
+    noise_floor: f32,
+    // Samples waiting for a full frame
+    pending: Vec<f32>,
+    frames: usize,
+    // Index of the first speech frame, None while only silence was heard
+    first_speech_frame: Option<usize>,
+    last_speech_frame: usize,
+}
+
+impl VoiceDetector {
+    pub fn new(sample_rate: u32, threshold: f32) -> Self {
+        Self {
+            frame_len: (sample_rate * FRAME_DURATION_MS / 1000).max(1) as usize,
+            sample_rate,
+            threshold,
+            noise_floor: 0.0,
+            pending: vec![],
+            frames: 0,
+            first_speech_frame: None,
+            last_speech_frame: 0,
+        }
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        self.pending.extend_from_slice(samples);
+        let complete = self.pending.len() / self.frame_len * self.frame_len;
+        let frames: Vec<f32> = self.pending.drain(..complete).collect();
+        for frame in frames.chunks(self.frame_len) {
+            if self.is_speech(frame) {
+                self.first_speech_frame.get_or_insert(self.frames);
+                self.last_speech_frame = self.frames;
+            }
+            self.frames += 1;
+        }
+    }
+
+    pub fn speech_started(&self) -> bool {
+        self.first_speech_frame.is_some()
+    }
+
+    // Silence heard since the last speech frame, zero until someone speaks
+    pub fn trailing_silence(&self) -> Duration {
+        if !self.speech_started() {
+            return Duration::ZERO;
+        }
+        self.get_frames_duration(self.frames - self.last_speech_frame - 1)
+    }
+
+    // Sample where the speech starts, pre-roll included
+    pub fn speech_start(&self) -> Option<usize> {
+        let first_frame = self.first_speech_frame?;
+        let pre_roll = (PRE_ROLL.as_secs_f32() * self.sample_rate as f32) as usize;
+        Some((first_frame * self.frame_len).saturating_sub(pre_roll))
+    }
+
+    fn is_speech(&mut self, frame: &[f32]) -> bool {
+        let rms = (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt();
+        let crossings = frame
+            .windows(2)
+            .filter(|pair| (pair[0] >= 0.0) != (pair[1] >= 0.0))
+            .count();
+        let zero_crossing_rate = crossings as f32 / frame.len() as f32;
+
+        let is_speech = rms > self.threshold.max(self.noise_floor * NOISE_FLOOR_RATIO)
+            && zero_crossing_rate < MAX_ZERO_CROSSING_RATE;
+        if !is_speech {
+            // Follow the background noise slowly so a short pause does not raise it
+            self.noise_floor = if self.noise_floor == 0.0 {
+                rms
+            } else {
+                self.noise_floor * 0.95 + rms * 0.05
+            };
+        }
+        is_speech
+    }
+
+    fn get_frames_duration(&self, frames: usize) -> Duration {
+        Duration::from_millis((frames as u32 * FRAME_DURATION_MS) as u64)
+    }
+}
+
+// Drop the silence before the first word, everything when nobody spoke
+pub fn trim_leading_silence(samples: &[f32], sample_rate: u32, threshold: f32) -> &[f32] {
+    let mut detector = VoiceDetector::new(sample_rate, threshold);
+    detector.push(samples);
+    match detector.speech_start() {
+        Some(start) => &samples[start..],
+        None => &[],
+    }
+}
+
+// Silence after speech that stops the recording, None when only the user stops it
+pub fn get_silence_limit(
+    vad_silence_ms: u64,
+    conversation_mode: bool,
+    push_to_talk: bool,
+) -> Option<Duration> {
+    if push_to_talk {
+        return None;
+    }
+    match vad_silence_ms {
+        // Hands-free needs the turn to end by itself
+        0 if conversation_mode => Some(CONVERSATION_SILENCE),
+        0 => None,
+        ms => Some(Duration::from_millis(ms)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::test_signals::{SIGNAL_RATE, get_silence, get_voice};
+
+    const RATE: u32 = SIGNAL_RATE as u32;
+    const THRESHOLD: f32 = 0.01;
+
+    // Loud but alternating every sample, like hiss
+    fn get_hiss(ms: usize) -> Vec<f32> {
+        (0..RATE as usize * ms / 1000)
+            .map(|i| if i % 2 == 0 { 0.3 } else { -0.3 })
+            .collect()
+    }
+
+    #[test]
+    fn trailing_silence_is_counted_after_speech_only() {
+        let mut detector = VoiceDetector::new(RATE, THRESHOLD);
+
+        detector.push(&get_silence(1000));
+        assert!(!detector.speech_started());
+        assert_eq!(detector.trailing_silence(), Duration::ZERO);
+
+        detector.push(&get_voice(500));
+        assert!(detector.speech_started());
+        detector.push(&get_silence(600));
+        assert_eq!(detector.trailing_silence(), Duration::from_millis(600));
+
+        detector.push(&get_voice(100));
+        assert_eq!(detector.trailing_silence(), Duration::ZERO);
+    }
+
+    #[test]
+    fn hiss_is_not_speech() {
+        let mut detector = VoiceDetector::new(RATE, THRESHOLD);
+
+        detector.push(&get_hiss(500));
+
+        assert!(!detector.speech_started());
+    }
+
+    #[test]
+    fn leading_silence_is_trimmed_with_pre_roll() {
+        let samples = [get_silence(1000), get_voice(300), get_silence(300)].concat();
+
+        let trimmed = trim_leading_silence(&samples, RATE, THRESHOLD);
+
+        // 1 s of silence minus 200 ms of pre-roll
+        assert_eq!(trimmed.len(), samples.len() - 800 * 16);
+        assert!(trim_leading_silence(&get_silence(500), RATE, THRESHOLD).is_empty());
+    }
+
+    #[test]
+    fn silence_limit_follows_the_mode() {
+        assert_eq!(
+            get_silence_limit(800, false, false),
+            Some(Duration::from_millis(800))
+        );
+        assert_eq!(get_silence_limit(0, false, false), None);
+        // Conversation mode always ends the turn by itself
+        assert_eq!(
+            get_silence_limit(0, true, false),
+            Some(CONVERSATION_SILENCE)
+        );
+        assert_eq!(
+            get_silence_limit(800, true, false),
+            Some(Duration::from_millis(800))
+        );
+        // The key release ends a push-to-talk recording
+        assert_eq!(get_silence_limit(800, true, true), None);
+    }
+}
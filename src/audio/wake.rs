@@ -0,0 +1,174 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::{
+    audio::{
+        micro::{RingBuffer, start_input_stream},
+        resample::{downmix_to_mono, resample_to_16k},
+        vad::VoiceDetector,
+    },
+    config::AppConfig,
+};
+
+// Audio kept while waiting for the wake phrase, long enough to say it
+const WAKE_WINDOW: Duration = Duration::from_secs(3);
+// The phrase is checked once the speaker pauses this long
+const WAKE_PAUSE: Duration = Duration::from_millis(300);
+
+// Keeps the last seconds heard by the microphone and hands them over when someone spoke
+pub struct WakeWordListener {
+    stream: Option<cpal::Stream>,
+    buffer: Arc<Mutex<RingBuffer>>,
+    stream_error: Arc<Mutex<Option<String>>>,
+    input_device: String,
+    vad_threshold: f32,
+    config: cpal::StreamConfig,
+    pub listening: bool,
+}
+
+impl fmt::Debug for WakeWordListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WakeWordListener")
+            .field("input_device", &self.input_device)
+            .field("config", &self.config)
+            .field("listening", &self.listening)
+            .finish()
+    }
+}
+
+impl WakeWordListener {
+    pub fn new(app_config: &AppConfig) -> Self {
+        Self {
+            stream: None,
+            buffer: Arc::new(Mutex::new(RingBuffer::default())),
+            stream_error: Arc::new(Mutex::new(None)),
+            input_device: app_config.input_device.clone(),
+            vad_threshold: app_config.vad_threshold,
+            config: cpal::StreamConfig {
+                channels: 1,
+                sample_rate: cpal::SampleRate(16000),
+                buffer_size: cpal::BufferSize::Default,
+            },
+            listening: false,
+        }
+    }
+
+    // The listener must be started again to use them
+    pub fn set_config(&mut self, app_config: &AppConfig) {
+        self.input_device = app_config.input_device.clone();
+        self.vad_threshold = app_config.vad_threshold;
+    }
+
+    pub fn start(&mut self) -> Result<(), String> {
+        let input = start_input_stream(
+            &self.input_device,
+            self.buffer.clone(),
+            self.stream_error.clone(),
+            |buffer, config| {
+                let capacity = (WAKE_WINDOW.as_secs_f32()
+                    * config.sample_rate.0 as f32
+                    * config.channels as f32) as usize;
+                *buffer = RingBuffer::new(capacity);
+            },
+        )?;
+        self.config = input.config;
+        self.stream = Some(input.stream);
+        self.listening = true;
+        println!("👂 Listening for the wake word...");
+        Ok(())
+    }
+
+    // Frees the microphone, e.g. for a recording
+    pub fn stop(&mut self) {
+        self.stream = None;
+        self.listening = false;
+    }
+
+    // Mono 16 kHz audio of what was just said, once the speaker paused.
+    // The buffer is emptied so the same words are not checked twice.
+    pub fn take_utterance(&self) -> Option<Vec<f32>> {
+        let mut buffer = self.buffer.lock().unwrap();
+        let mono = downmix_to_mono(&buffer.to_vec(), self.config.channels);
+        let mut detector = VoiceDetector::new(self.config.sample_rate.0, self.vad_threshold);
+        detector.push(&mono);
+        if !detector.speech_started() || detector.trailing_silence() < WAKE_PAUSE {
+            return None;
+        }
+        buffer.clear();
+        Some(resample_to_16k(&mono, self.config.sample_rate.0))
+    }
+
+    // Device failure, e.g. unplugged, the app stops listening
+    pub fn take_stream_error(&self) -> Option<String> {
+        self.stream_error.lock().unwrap().take()
+    }
+}
+
+// Whether the transcription contains the phrase, whatever the case and punctuation
+pub fn contains_wake_phrase(text: &str, phrase: &str) -> bool {
+    let phrase = normalize(phrase);
+    !phrase.is_empty() && format!(" {} ", normalize(text)).contains(&format!(" {} ", phrase))
+}
+
+// Lowercase words separated by single spaces, "Hey, Potato!" is "hey potato"
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::micro::SampleSink;
+    use crate::audio::test_signals::{SIGNAL_RATE as RATE, get_silence, get_voice};
+    use crate::config::get_default_config;
+
+    // Same as a started listener on a 16 kHz mono microphone, without the device
+    fn get_listener(samples: &[f32]) -> WakeWordListener {
+        let listener = WakeWordListener::new(&get_default_config());
+        let mut buffer = RingBuffer::new(WAKE_WINDOW.as_secs() as usize * RATE);
+        buffer.extend_samples(samples.iter().copied());
+        *listener.buffer.lock().unwrap() = buffer;
+        listener
+    }
+
+    #[test]
+    fn utterance_is_taken_once_the_speaker_pauses() {
+        let listener = get_listener(&[get_silence(500), get_voice(800), get_silence(400)].concat());
+
+        let utterance = listener.take_utterance().unwrap();
+
+        assert_eq!(utterance.len(), RATE * 1700 / 1000);
+        // Not checked twice
+        assert!(listener.buffer.lock().unwrap().to_vec().is_empty());
+        assert_eq!(listener.take_utterance(), None);
+    }
+
+    #[test]
+    fn silence_and_unfinished_speech_are_not_taken() {
+        assert_eq!(get_listener(&get_silence(2000)).take_utterance(), None);
+
+        let listener = get_listener(&[get_silence(500), get_voice(800), get_silence(100)].concat());
+
+        assert_eq!(listener.take_utterance(), None);
+        // Kept until the pause is long enough
+        assert!(!listener.buffer.lock().unwrap().to_vec().is_empty());
+    }
+
+    #[test]
+    fn wake_phrase_ignores_case_and_punctuation() {
+        assert!(contains_wake_phrase("Hey, Potato!", "hey potato"));
+        assert!(contains_wake_phrase(
+            " So... hey potato, what time is it?",
+            "Hey Potato"
+        ));
+        // Whole words only
+        assert!(!contains_wake_phrase("They potatoes", "hey potato"));
+        assert!(!contains_wake_phrase("Hello there", "hey potato"));
+        assert!(!contains_wake_phrase("Hey potato", " "));
+    }
+}
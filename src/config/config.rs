@@ -1,4 +1,4 @@
-use std::{fmt::format, fs, path::PathBuf};
+use std::{fs, path::PathBuf};
 
 use dotenvy::dotenv;
 use serde::{Deserialize, Serialize};
@@ -7,9 +7,29 @@ use serde::{Deserialize, Serialize};
 pub enum AiProvider {
     OpenAI,
     Gemini,
+    Ollama,
+    Anthropic,
     Custom,
 }
 
+// How Whisper picks the transcribed words
+#[derive(Debug, Deserialize, Clone, Serialize, PartialEq)]
+pub enum SttSampling {
+    // Fastest, keeps the most likely token at each step
+    Greedy,
+    // Slower but more accurate, keeps stt_beam_size candidates
+    BeamSearch,
+}
+
+// Local program turning the answers into speech
+#[derive(Debug, Deserialize, Clone, Serialize, PartialEq)]
+pub enum TtsEngine {
+    // espeak-ng, tts_voice is a voice name such as "en"
+    Espeak,
+    // Piper, tts_voice is the path of a .onnx voice
+    Piper,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct UserConfig {
     pub api_key: Option<String>,
@@ -19,17 +39,75 @@ pub struct UserConfig {
     pub ai_provider: Option<AiProvider>,
     pub ai_model: Option<String>,
     pub ai_url: Option<String>,
+    pub stream_responses: Option<bool>,
+    pub request_timeout_secs: Option<u64>,
+    pub max_retries: Option<u32>,
+    pub retry_base_delay_ms: Option<u64>,
+    pub input_device: Option<String>,
+    pub stt_model: Option<String>,
+    pub vad_threshold: Option<f32>,
+    pub vad_silence_ms: Option<u64>,
+    pub stt_streaming: Option<bool>,
+    pub stt_threads: Option<u32>,
+    pub stt_sampling: Option<SttSampling>,
+    pub stt_beam_size: Option<u32>,
+    pub tts_engine: Option<TtsEngine>,
+    pub tts_voice: Option<String>,
+    pub tts_auto_speak: Option<bool>,
+    pub barge_in_threshold: Option<f32>,
+    pub key_push_to_talk: Option<String>,
+    pub key_new_conversation: Option<String>,
+    pub key_settings: Option<String>,
+    pub key_stop_generation: Option<String>,
+    pub key_edit_last_message: Option<String>,
+    pub wake_word_enabled: Option<bool>,
+    pub wake_phrase: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone, Serialize)]
 pub struct AppConfig {
     pub api_key: String,
     pub ai_url: String,
+    // Spoken language ("en", "fr"...), "auto" lets Whisper detect it.
+    // A locale list such as "en_US:en", as desktops set LANGUAGE, gives its first language
     pub language: String,
     pub volume: u8,
     pub debug_mode: bool,
     pub ai_model: String,
     pub ai_provider: AiProvider,
+    pub stream_responses: bool,
+    pub request_timeout_secs: u64,
+    pub max_retries: u32,
+    pub retry_base_delay_ms: u64,
+    // Microphone name as listed in the settings, empty for the system default
+    pub input_device: String,
+    // Minimum RMS level (0 to 1) considered as speech
+    pub vad_threshold: f32,
+    // Recording stops after this much silence following speech, 0 to stop by hand only
+    pub vad_silence_ms: u64,
+    // Whisper model path, file name or short name ("base.en")
+    pub stt_model: String,
+    // Transcribe while recording and show the text as it comes
+    pub stt_streaming: bool,
+    pub stt_threads: u32,
+    pub stt_sampling: SttSampling,
+    pub stt_beam_size: u32,
+    pub tts_engine: TtsEngine,
+    pub tts_voice: String,
+    // Read every answer aloud as soon as it arrives
+    pub tts_auto_speak: bool,
+    // Minimum RMS level (0 to 1) of a voice interrupting the assistant in conversation mode,
+    // above the echo of its own voice
+    pub barge_in_threshold: f32,
+    // Keyboard shortcuts such as "Ctrl+N", "Escape" or "F2", empty to disable one
+    pub key_push_to_talk: String,
+    pub key_new_conversation: String,
+    pub key_settings: String,
+    pub key_stop_generation: String,
+    pub key_edit_last_message: String,
+    // Listen in the background and start recording when the wake phrase is heard
+    pub wake_word_enabled: bool,
+    pub wake_phrase: String,
 }
 
 impl AppConfig {
@@ -42,11 +120,52 @@ impl AppConfig {
             ai_url: user_config.ai_url.unwrap_or(self.ai_url),
             ai_model: user_config.ai_model.unwrap_or(self.ai_model),
             ai_provider: user_config.ai_provider.unwrap_or(self.ai_provider),
+            stream_responses: user_config
+                .stream_responses
+                .unwrap_or(self.stream_responses),
+            request_timeout_secs: user_config
+                .request_timeout_secs
+                .unwrap_or(self.request_timeout_secs),
+            max_retries: user_config.max_retries.unwrap_or(self.max_retries),
+            retry_base_delay_ms: user_config
+                .retry_base_delay_ms
+                .unwrap_or(self.retry_base_delay_ms),
+            input_device: user_config.input_device.unwrap_or(self.input_device),
+            vad_threshold: user_config.vad_threshold.unwrap_or(self.vad_threshold),
+            vad_silence_ms: user_config.vad_silence_ms.unwrap_or(self.vad_silence_ms),
+            stt_model: user_config.stt_model.unwrap_or(self.stt_model),
+            stt_streaming: user_config.stt_streaming.unwrap_or(self.stt_streaming),
+            stt_threads: user_config.stt_threads.unwrap_or(self.stt_threads),
+            stt_sampling: user_config.stt_sampling.unwrap_or(self.stt_sampling),
+            stt_beam_size: user_config.stt_beam_size.unwrap_or(self.stt_beam_size),
+            tts_engine: user_config.tts_engine.unwrap_or(self.tts_engine),
+            tts_voice: user_config.tts_voice.unwrap_or(self.tts_voice),
+            tts_auto_speak: user_config.tts_auto_speak.unwrap_or(self.tts_auto_speak),
+            barge_in_threshold: user_config
+                .barge_in_threshold
+                .unwrap_or(self.barge_in_threshold),
+            key_push_to_talk: user_config
+                .key_push_to_talk
+                .unwrap_or(self.key_push_to_talk),
+            key_new_conversation: user_config
+                .key_new_conversation
+                .unwrap_or(self.key_new_conversation),
+            key_settings: user_config.key_settings.unwrap_or(self.key_settings),
+            key_stop_generation: user_config
+                .key_stop_generation
+                .unwrap_or(self.key_stop_generation),
+            key_edit_last_message: user_config
+                .key_edit_last_message
+                .unwrap_or(self.key_edit_last_message),
+            wake_word_enabled: user_config
+                .wake_word_enabled
+                .unwrap_or(self.wake_word_enabled),
+            wake_phrase: user_config.wake_phrase.unwrap_or(self.wake_phrase),
         }
     }
 }
 
-fn get_default_config() -> AppConfig {
+pub fn get_default_config() -> AppConfig {
     AppConfig {
         ai_url: "".to_string(),
         api_key: "".to_string(),
@@ -55,6 +174,29 @@ fn get_default_config() -> AppConfig {
         language: "en".to_string(),
         volume: 50,
         debug_mode: false,
+        stream_responses: true,
+        request_timeout_secs: 60,
+        max_retries: 3,
+        retry_base_delay_ms: 500,
+        input_device: "".to_string(),
+        vad_threshold: 0.01,
+        vad_silence_ms: 1500,
+        stt_model: "ggml-base.en.bin".to_string(),
+        stt_streaming: true,
+        stt_threads: 4,
+        stt_sampling: SttSampling::Greedy,
+        stt_beam_size: 5,
+        tts_engine: TtsEngine::Espeak,
+        tts_voice: "en".to_string(),
+        tts_auto_speak: false,
+        barge_in_threshold: 0.1,
+        key_push_to_talk: "F2".to_string(),
+        key_new_conversation: "Ctrl+N".to_string(),
+        key_settings: "Ctrl+,".to_string(),
+        key_stop_generation: "Escape".to_string(),
+        key_edit_last_message: "ArrowUp".to_string(),
+        wake_word_enabled: false,
+        wake_phrase: "hey potato".to_string(),
     }
 }
 
@@ -117,3 +259,47 @@ pub fn save_user_settings(new_settings: AppConfig) -> Result<(), String> {
         .map_err(|e| format!("Cannot serialize new config: {}", e))?;
     fs::write(config_path, config_str).map_err(|e| format!("Cannot write settings file: {}", e))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_config_accepts_every_provider() {
+        for provider in ["OpenAI", "Gemini", "Ollama", "Anthropic", "Custom"] {
+            let user_config: UserConfig =
+                toml::from_str(&format!("ai_provider = \"{}\"", provider)).unwrap();
+            assert!(user_config.ai_provider.is_some());
+        }
+    }
+
+    #[test]
+    fn user_config_reads_stt_settings() {
+        let user_config: UserConfig = toml::from_str(
+            "language = \"auto\"\nstt_threads = 8\nstt_sampling = \"BeamSearch\"\nstt_beam_size = 3",
+        )
+        .unwrap();
+        let config = get_default_config().override_with(user_config);
+
+        assert_eq!(config.language, "auto");
+        assert_eq!(config.stt_threads, 8);
+        assert_eq!(config.stt_sampling, SttSampling::BeamSearch);
+        assert_eq!(config.stt_beam_size, 3);
+    }
+
+    #[test]
+    fn app_config_round_trips_new_providers() {
+        for provider in [AiProvider::Ollama, AiProvider::Anthropic] {
+            let config = AppConfig {
+                ai_provider: provider,
+                ..get_default_config()
+            };
+            let serialized = toml::to_string(&config).unwrap();
+            let parsed: AppConfig = toml::from_str(&serialized).unwrap();
+            assert_eq!(
+                format!("{:?}", parsed.ai_provider),
+                format!("{:?}", config.ai_provider)
+            );
+        }
+    }
+}
@@ -1,16 +1,136 @@
-use std::{collections::HashMap, fs::create_dir, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fs::{self, create_dir},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use crate::AiMessage;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{AiMessage, config::AiProvider};
+
+// Bump when the file layout changes, files written by a newer version are skipped
+pub const HISTORY_SCHEMA_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conversation {
+    pub id: Uuid,
+    pub title: String,
+    // Unix timestamps in seconds
+    pub created_at: u64,
+    pub updated_at: u64,
+    // Provider and model that answered last, unknown until the first request
+    #[serde(default)]
+    pub provider: Option<AiProvider>,
+    #[serde(default)]
+    pub model: Option<String>,
+    pub messages: Vec<AiMessage>,
+}
+
+impl Conversation {
+    pub fn new(title: &str) -> Self {
+        let now = get_timestamp();
+        Self {
+            id: Uuid::new_v4(),
+            title: title.to_string(),
+            created_at: now,
+            updated_at: now,
+            provider: None,
+            model: None,
+            messages: vec![],
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct History {
-    pub conversations: HashMap<String, Vec<AiMessage>>,
+    pub conversations: HashMap<Uuid, Conversation>,
+}
+
+// On disk representation of a single conversation
+#[derive(Debug, Serialize, Deserialize)]
+struct ConversationFile {
+    schema_version: u32,
+    #[serde(flatten)]
+    conversation: Conversation,
+}
+
+// Schema 1 files, keyed by their name and without metadata
+#[derive(Debug, Deserialize)]
+struct LegacyConversationFile {
+    name: String,
+    messages: Vec<AiMessage>,
+}
+
+impl History {
+    // Most recently active conversations first
+    pub fn get_conversations(&self) -> Vec<&Conversation> {
+        let mut conversations: Vec<&Conversation> = self.conversations.values().collect();
+        conversations.sort_by(|a, b| {
+            b.updated_at
+                .cmp(&a.updated_at)
+                .then_with(|| a.title.cmp(&b.title))
+        });
+        conversations
+    }
+
+    pub fn get_messages(&self, id: &Uuid) -> &[AiMessage] {
+        self.conversations
+            .get(id)
+            .map(|conversation| conversation.messages.as_slice())
+            .unwrap_or_default()
+    }
+
+    pub fn get_messages_mut(&mut self, id: &Uuid) -> Option<&mut Vec<AiMessage>> {
+        self.conversations
+            .get_mut(id)
+            .map(|conversation| &mut conversation.messages)
+    }
+
+    pub fn push_message(&mut self, id: &Uuid, message: AiMessage) {
+        if let Some(messages) = self.get_messages_mut(id) {
+            messages.push(message);
+        }
+    }
+
+    // Add an empty conversation and return its id
+    pub fn create_conversation(&mut self, title: &str) -> Result<Uuid, String> {
+        let title = title.trim();
+        if title.is_empty() {
+            return Err("The conversation name cannot be empty".to_string());
+        }
+        let conversation = Conversation::new(title);
+        let id = conversation.id;
+        self.conversations.insert(id, conversation);
+        self.save_conversation(&id);
+        Ok(id)
+    }
+
+    // Remember who answered, shown again when the conversation is reopened
+    pub fn set_model(&mut self, id: &Uuid, provider: &AiProvider, model: &str) {
+        if let Some(conversation) = self.conversations.get_mut(id) {
+            conversation.provider = Some(provider.clone());
+            conversation.model = Some(model.to_string());
+        }
+    }
+
+    // Write the conversation kept in memory to its own file right away
+    pub fn save_conversation(&mut self, id: &Uuid) {
+        let Some(conversation) = self.conversations.get_mut(id) else {
+            return;
+        };
+        conversation.updated_at = get_timestamp();
+        if let Err(e) = write_conversation(&get_history_folder_path(), conversation) {
+            println!("Cannot save conversation {}: {}", conversation.title, e);
+        }
+    }
 }
 
 pub fn get_history() -> History {
     let config_path = get_history_folder_path();
     if !config_path.exists() {
-        match create_dir(config_path) {
+        match create_dir(&config_path) {
             Ok(_) => {
                 println!("History folder created");
             }
@@ -21,8 +141,106 @@ pub fn get_history() -> History {
     }
 
     History {
-        conversations: HashMap::new(),
+        conversations: load_conversations(&config_path),
+    }
+}
+
+fn load_conversations(folder: &Path) -> HashMap<Uuid, Conversation> {
+    let mut conversations = HashMap::new();
+    let entries = match fs::read_dir(folder) {
+        Ok(entries) => entries,
+        Err(e) => {
+            println!("Cannot read history folder: {}", e);
+            return conversations;
+        }
+    };
+
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        match read_conversation(&path) {
+            Ok(conversation) => {
+                conversations.insert(conversation.id, conversation);
+            }
+            Err(e) => println!("Skipping history file {}: {}", path.display(), e),
+        }
+    }
+
+    conversations
+}
+
+fn read_conversation(path: &Path) -> Result<Conversation, String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let value: serde_json::Value =
+        serde_json::from_str(&content).map_err(|e| format!("Invalid history file: {}", e))?;
+    let schema_version = value["schema_version"].as_u64().unwrap_or_default();
+
+    if schema_version > HISTORY_SCHEMA_VERSION as u64 {
+        return Err(format!(
+            "Written by a newer version (schema {})",
+            schema_version
+        ));
+    }
+    if schema_version < HISTORY_SCHEMA_VERSION as u64 {
+        return migrate_legacy_conversation(path, value);
+    }
+    serde_json::from_value::<ConversationFile>(value)
+        .map(|file| file.conversation)
+        .map_err(|e| format!("Invalid history file: {}", e))
+}
+
+// Give a schema 1 conversation an id and move it to its new file
+fn migrate_legacy_conversation(
+    path: &Path,
+    value: serde_json::Value,
+) -> Result<Conversation, String> {
+    let legacy: LegacyConversationFile =
+        serde_json::from_value(value).map_err(|e| format!("Invalid history file: {}", e))?;
+    let mut conversation = Conversation::new(&legacy.name);
+    conversation.messages = legacy.messages;
+    // Last write is the best guess for the last activity
+    if let Some(modified) = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+    {
+        conversation.created_at = modified.as_secs();
+        conversation.updated_at = modified.as_secs();
     }
+
+    let folder = path.parent().unwrap_or(Path::new("."));
+    write_conversation(folder, &conversation)?;
+    if let Err(e) = fs::remove_file(path) {
+        println!("Cannot remove migrated file {}: {}", path.display(), e);
+    }
+    Ok(conversation)
+}
+
+fn write_conversation(folder: &Path, conversation: &Conversation) -> Result<(), String> {
+    let file = ConversationFile {
+        schema_version: HISTORY_SCHEMA_VERSION,
+        conversation: conversation.clone(),
+    };
+    let content = serde_json::to_string_pretty(&file)
+        .map_err(|e| format!("Cannot serialize conversation: {}", e))?;
+
+    // Write next to the target then rename, a crash never leaves a half written file
+    let path = get_conversation_path(folder, &conversation.id);
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, content).map_err(|e| format!("Cannot write history file: {}", e))?;
+    fs::rename(&tmp_path, &path).map_err(|e| format!("Cannot write history file: {}", e))
+}
+
+fn get_conversation_path(folder: &Path, id: &Uuid) -> PathBuf {
+    folder.join(format!("{}.json", id))
+}
+
+fn get_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
 }
 
 fn get_history_folder_path() -> PathBuf {
@@ -30,3 +248,99 @@ fn get_history_folder_path() -> PathBuf {
         .unwrap_or_else(|| PathBuf::from("."))
         .join(".potato_history")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AiMessageFrom, api::error::AiError};
+
+    fn get_test_folder(name: &str) -> PathBuf {
+        let folder =
+            std::env::temp_dir().join(format!("potato_history_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    #[test]
+    fn conversations_round_trip_through_files() {
+        let folder = get_test_folder("round_trip");
+        let mut reply = AiMessage::new(AiMessageFrom::Assistant, "Hello".to_string());
+        reply.truncated = true;
+        let mut conversation = Conversation::new("Potato talk");
+        conversation.provider = Some(AiProvider::Ollama);
+        conversation.model = Some("llama-potato".to_string());
+        conversation.messages = vec![
+            AiMessage::new(AiMessageFrom::User, "Hi".to_string()),
+            reply,
+            AiMessage::from_error(AiError::Network("offline".to_string())),
+        ];
+
+        write_conversation(&folder, &conversation).unwrap();
+        let conversations = load_conversations(&folder);
+
+        let loaded = &conversations[&conversation.id];
+        assert_eq!(loaded.title, "Potato talk");
+        assert_eq!(loaded.model, Some("llama-potato".to_string()));
+        assert_eq!(loaded.created_at, conversation.created_at);
+        assert_eq!(loaded.messages.len(), 3);
+        assert_eq!(loaded.messages[0].content, "Hi");
+        assert!(loaded.messages[1].truncated);
+        assert_eq!(
+            loaded.messages[2].error,
+            Some(AiError::Network("offline".to_string()))
+        );
+        assert!(folder.join(format!("{}.json", conversation.id)).exists());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn legacy_files_are_migrated_to_ids() {
+        let folder = get_test_folder("legacy");
+        fs::write(
+            folder.join("Potato_talk.json"),
+            r#"{"schema_version":1,"name":"Potato talk","messages":[{"from":"User","content":"Hi"}]}"#,
+        )
+        .unwrap();
+
+        let conversations = load_conversations(&folder);
+
+        let conversation = conversations.values().next().unwrap();
+        assert_eq!(conversation.title, "Potato talk");
+        assert_eq!(conversation.messages[0].content, "Hi");
+        assert!(!folder.join("Potato_talk.json").exists());
+        // Reloading gives back the same id
+        assert!(load_conversations(&folder).contains_key(&conversation.id));
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn conversations_are_ordered_by_last_activity() {
+        let mut old = Conversation::new("Same title");
+        old.updated_at = 10;
+        let mut recent = Conversation::new("Same title");
+        recent.updated_at = 20;
+        let history = History {
+            conversations: HashMap::from([(old.id, old.clone()), (recent.id, recent.clone())]),
+        };
+
+        let ids: Vec<Uuid> = history.get_conversations().iter().map(|c| c.id).collect();
+
+        assert_eq!(ids, vec![recent.id, old.id]);
+    }
+
+    #[test]
+    fn unknown_or_newer_files_are_skipped() {
+        let folder = get_test_folder("skipped");
+        fs::write(folder.join("broken.json"), "{ not json").unwrap();
+        fs::write(
+            folder.join("future.json"),
+            r#"{"schema_version":999,"name":"future","messages":[]}"#,
+        )
+        .unwrap();
+        fs::write(folder.join("notes.txt"), "ignored").unwrap();
+
+        assert!(load_conversations(&folder).is_empty());
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
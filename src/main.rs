@@ -1,36 +1,96 @@
-use std::fmt::format;
-
 use iced::{
-    Element, Font, Task, Theme,
+    Element, Subscription, Task, Theme, event, task, time,
     widget::{container, text_editor},
 };
+use serde::{Deserialize, Serialize};
+use std::{sync::Arc, time::Duration};
+use uuid::Uuid;
 
 use crate::{
-    audio::{micro::AudioRecorder, stt::LocalTranscriber},
+    api::{
+        error::AiError,
+        providers::{ChatProvider, get_provider},
+    },
+    audio::{
+        micro::{AudioRecorder, MicLevel, list_input_devices},
+        models::{SttModel, get_models_folders, list_models},
+        resample::WHISPER_SAMPLE_RATE,
+        segmenter::SentenceSegmenter,
+        speaker::Speaker,
+        stt::{LiveTranscript, LocalTranscriber, SttOptions, Transcript, join_text},
+        vad::get_silence_limit,
+        wake::{WakeWordListener, contains_wake_phrase},
+    },
     config::{AppConfig, get_config, save_user_settings},
     history::history::{History, get_history},
     ui::{
-        chat::get_chat_view, messages::UIMessage, new_conversation::get_new_conversation_view,
-        settings::get_settings_view, views::AppView,
+        chat::get_chat_view,
+        messages::UIMessage,
+        new_conversation::get_new_conversation_view,
+        settings::get_settings_view,
+        shortcuts::{KeyBinding, Shortcut, get_key_message, get_shortcut},
+        views::AppView,
     },
 };
 
+mod api;
 mod audio;
+#[allow(clippy::module_inception)]
 mod config;
+#[allow(clippy::module_inception)]
 mod history;
 mod ui;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum AiMessageFrom {
     User,
     System,
     Assistant,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AiMessage {
     pub from: AiMessageFrom,
     pub content: String,
+    // The generation was stopped before the answer was complete
+    #[serde(default)]
+    pub truncated: bool,
+    // Shown in the chat but never sent back to the AI
+    #[serde(default)]
+    pub error: Option<AiError>,
+}
+
+impl AiMessage {
+    pub fn new(from: AiMessageFrom, content: String) -> Self {
+        Self {
+            from,
+            content,
+            truncated: false,
+            error: None,
+        }
+    }
+
+    pub fn from_error(error: AiError) -> Self {
+        Self {
+            from: AiMessageFrom::Assistant,
+            content: error.to_string(),
+            truncated: false,
+            error: Some(error),
+        }
+    }
+}
+
+// Time between two live transcriptions of the recording
+const PARTIAL_TRANSCRIPTION_INTERVAL: Duration = Duration::from_secs(1);
+
+// Conversation opened when the history is empty
+const DEFAULT_CONVERSATION: &str = "Default";
+
+// AI request in flight and the conversation its answer belongs to
+#[derive(Debug)]
+pub struct Generation {
+    pub handle: task::Handle,
+    pub conversation: Uuid,
 }
 
 #[derive(Debug)]
@@ -39,25 +99,106 @@ struct PotatoApp {
     pub view: AppView,
     pub user_input: String,
     pub new_conv_input: String,
-    pub messages: Vec<AiMessage>,
+    pub active_conversation: Uuid,
     pub user_settings: text_editor::Content,
     pub input_error: String,
     pub history: History,
     pub audio_rec: AudioRecorder,
+    // Why recording is not possible, e.g. no microphone plugged in
+    pub mic_error: Option<String>,
+    // Refreshed while recording for the level meter
+    pub mic_level: MicLevel,
+    pub input_devices: Vec<String>,
+    // Why speech to text is unavailable when the Whisper model cannot be loaded
+    pub transcriber: Result<Arc<LocalTranscriber>, String>,
+    pub stt_models: Vec<SttModel>,
+    pub transcribing: bool,
+    // Text typed before the recording, the transcription goes after it
+    pub input_before_recording: String,
+    pub live_transcript: LiveTranscript,
+    pub partial_transcribing: bool,
+    // Changes whenever the live transcript starts over, late partial results are dropped
+    pub recording_id: u64,
+    // Recording time at which the next live transcription may start
+    pub next_partial_at: Duration,
+    // Language Whisper heard last, only known when the language is "auto"
+    pub detected_language: Option<String>,
+    pub generation: Option<Generation>,
+    pub speaker: Speaker,
+    // Sentences of the answer being received, spoken as soon as they are complete
+    pub reply_sentences: SentenceSegmenter,
+    // The user silenced the answer being received, its next sentences are not spoken
+    pub reply_muted: bool,
+    // Synthesis of the text being turned into speech, aborted by StopSpeaking
+    pub speech_task: Option<task::Handle>,
+    // Changes whenever the speaker is stopped, speech synthesized before is dropped
+    pub speech_id: u64,
+    // Why the last message could not be spoken
+    pub speaker_error: Option<String>,
+    // Hands-free loop: listen, transcribe, send, speak the answer and listen again
+    pub conversation_mode: bool,
+    // Recording while the push-to-talk key is held
+    pub push_to_talk: bool,
+    // Conversation and index of the message being edited, replaced when the input is sent
+    pub editing_message: Option<(Uuid, usize)>,
+    pub wake_listener: WakeWordListener,
+    // Whisper is looking for the wake phrase in what was just heard
+    pub wake_checking: bool,
+    // Why the wake word listener stopped, it is not restarted until the settings are saved
+    pub wake_error: Option<String>,
+    pub provider: Box<dyn ChatProvider>,
+    pub available_models: Vec<String>,
 }
 
 impl Default for PotatoApp {
     fn default() -> Self {
+        let config = get_config();
+        let mut history = get_history();
+        let active_conversation = match history.get_conversations().first() {
+            Some(conversation) => conversation.id,
+            None => history
+                .create_conversation(DEFAULT_CONVERSATION)
+                .unwrap_or_default(),
+        };
         Self {
-            config: get_config(),
+            active_conversation,
+            history,
+            provider: get_provider(&config),
+            transcriber: LocalTranscriber::from_config(&config).map(Arc::new),
+            audio_rec: AudioRecorder::new(&config),
+            speaker: Speaker::new(&config),
+            wake_listener: WakeWordListener::new(&config),
+            available_models: vec![],
+            config,
             view: AppView::Chat,
             user_input: "".to_string(),
             new_conv_input: "".to_string(),
-            messages: vec![],
             user_settings: text_editor::Content::new(),
             input_error: "".to_string(),
-            history: get_history(),
-            audio_rec: AudioRecorder::new().unwrap(),
+            mic_error: list_input_devices()
+                .is_empty()
+                .then(|| "No microphone found".to_string()),
+            mic_level: MicLevel::default(),
+            input_devices: vec![],
+            stt_models: vec![],
+            transcribing: false,
+            input_before_recording: "".to_string(),
+            live_transcript: LiveTranscript::default(),
+            partial_transcribing: false,
+            recording_id: 0,
+            next_partial_at: Duration::ZERO,
+            detected_language: None,
+            generation: None,
+            reply_sentences: SentenceSegmenter::default(),
+            reply_muted: false,
+            speech_task: None,
+            speech_id: 0,
+            speaker_error: None,
+            conversation_mode: false,
+            push_to_talk: false,
+            editing_message: None,
+            wake_checking: false,
+            wake_error: None,
         }
     }
 }
@@ -67,19 +208,130 @@ impl PotatoApp {
         match message {
             UIMessage::None => Task::none(),
             UIMessage::UserInputHandle(new_value) => {
+                // Clearing the input gives up the edit, the old message stays
+                if new_value.trim().is_empty() {
+                    self.editing_message = None;
+                }
                 self.user_input = new_value;
                 Task::none()
             }
             UIMessage::SendMessage => {
-                self.messages.push(AiMessage {
-                    from: AiMessageFrom::User,
-                    content: self.user_input.clone(),
-                });
-                self.messages.push(AiMessage {
-                    from: AiMessageFrom::Assistant,
-                    content: "Roger".to_string(),
-                });
+                if self.user_input.trim().is_empty() || self.generation.is_some() {
+                    return Task::none();
+                }
+                let conversation = self.active_conversation;
+                // The edited message and what followed it are replaced by the new one
+                if let Some((id, index)) = self.editing_message.take()
+                    && id == conversation
+                    && let Some(messages) = self.history.get_messages_mut(&conversation)
+                {
+                    messages.truncate(index);
+                }
+                self.history.push_message(
+                    &conversation,
+                    AiMessage::new(AiMessageFrom::User, self.user_input.clone()),
+                );
+                self.history.set_model(
+                    &conversation,
+                    &self.config.ai_provider,
+                    &self.config.ai_model,
+                );
                 self.user_input = "".to_string();
+                self.reply_sentences = SentenceSegmenter::default();
+                self.reply_muted = false;
+                self.history.save_conversation(&conversation);
+                let ai_history = self.get_ai_history(&conversation);
+
+                let task = if self.config.stream_responses {
+                    // Empty assistant message filled as the chunks arrive
+                    self.history.push_message(
+                        &conversation,
+                        AiMessage::new(AiMessageFrom::Assistant, "".to_string()),
+                    );
+                    Task::run(self.provider.stream(ai_history), |chunk| match chunk {
+                        Ok(content) => UIMessage::StreamChunkReceived(content),
+                        Err(e) => UIMessage::StreamFailed(e),
+                    })
+                    .chain(Task::done(UIMessage::StreamFinished))
+                } else {
+                    Task::perform(self.provider.send(ai_history), UIMessage::ReceiveAiMessage)
+                };
+                let (task, handle) = task.abortable();
+                self.generation = Some(Generation {
+                    handle,
+                    conversation,
+                });
+                if self.conversation_mode {
+                    // Listen during the answer so the user can interrupt it
+                    return Task::batch([task, self.update(UIMessage::StartAudio)]);
+                }
+                task
+            }
+            UIMessage::ReceiveAiMessage(result) => {
+                let Some(generation) = self.generation.take() else {
+                    return Task::none();
+                };
+                let task = match result {
+                    Ok(content) => {
+                        let task = self.speak_reply(&content, true);
+                        self.history.push_message(
+                            &generation.conversation,
+                            AiMessage::new(AiMessageFrom::Assistant, content),
+                        );
+                        task
+                    }
+                    Err(e) => {
+                        self.push_error(&generation.conversation, e);
+                        Task::none()
+                    }
+                };
+                self.history.save_conversation(&generation.conversation);
+                task
+            }
+            UIMessage::StreamChunkReceived(content) => {
+                let Some(generation) = &self.generation else {
+                    return Task::none();
+                };
+                if let Some(last) = self
+                    .history
+                    .get_messages_mut(&generation.conversation)
+                    .and_then(|messages| messages.last_mut())
+                    && last.from == AiMessageFrom::Assistant
+                    && last.error.is_none()
+                {
+                    last.content.push_str(&content);
+                    return self.speak_reply(&content, false);
+                }
+                Task::none()
+            }
+            UIMessage::StreamFailed(e) => {
+                if let Some(generation) = &self.generation {
+                    let conversation = generation.conversation;
+                    // The text received before the failure stays, flagged as incomplete
+                    self.mark_reply_truncated(&conversation);
+                    self.push_error(&conversation, e);
+                }
+                Task::none()
+            }
+            UIMessage::StreamFinished => {
+                let Some(generation) = self.generation.take() else {
+                    return Task::none();
+                };
+                self.drop_empty_reply(&generation.conversation);
+                self.history.save_conversation(&generation.conversation);
+                self.speak_reply("", true)
+            }
+            UIMessage::StopGeneration => {
+                let Some(generation) = self.generation.take() else {
+                    return Task::none();
+                };
+                generation.handle.abort();
+                self.provider.cancel();
+                self.reply_sentences = SentenceSegmenter::default();
+                // Keep what was already received, flagged as incomplete
+                self.mark_reply_truncated(&generation.conversation);
+                self.drop_empty_reply(&generation.conversation);
+                self.history.save_conversation(&generation.conversation);
                 Task::none()
             }
             UIMessage::ChangeView(new_view) => {
@@ -89,18 +341,61 @@ impl PotatoApp {
                         Err(_) => "".to_string(),
                     };
                     self.user_settings = text_editor::Content::with_text(config_str.as_str());
+                    self.stt_models = list_models(&get_models_folders());
+                    self.input_devices = list_input_devices();
+                    self.view = new_view;
+                    return Task::perform(self.provider.list_models(), UIMessage::ModelsLoaded);
                 };
                 self.view = new_view;
                 Task::none()
             }
+            UIMessage::ModelsLoaded(result) => {
+                match result {
+                    Ok(models) => self.available_models = models,
+                    Err(e) => {
+                        self.available_models = vec![];
+                        self.input_error = format!("Cannot list AI models: {}", e);
+                    }
+                };
+                Task::none()
+            }
             UIMessage::SaveSettings => {
                 self.input_error = "".to_string();
                 match toml::from_str::<AppConfig>(&self.user_settings.text()) {
                     Ok(new_config) => {
+                        // The answer being generated is cut as with the Stop button
+                        let stop = self.update(UIMessage::StopGeneration);
+                        // Other requests running on the previous backend are not wanted anymore
+                        self.provider.cancel();
+                        self.provider = get_provider(&new_config);
+                        let stt_model_changed = new_config.stt_model != self.config.stt_model;
+                        self.audio_rec.set_config(&new_config);
+                        self.speaker.set_config(&new_config);
+                        // Started again by the next check when still enabled
+                        self.wake_listener.stop();
+                        self.wake_listener.set_config(&new_config);
+                        self.wake_error = None;
                         self.config = new_config.clone();
                         if save_user_settings(new_config).is_err() {
                             self.input_error = "Error when writing user config".to_string();
                         };
+                        if stt_model_changed {
+                            let config = self.config.clone();
+                            return Task::batch([
+                                stop,
+                                Task::perform(
+                                    async move {
+                                        tokio::task::spawn_blocking(move || {
+                                            LocalTranscriber::from_config(&config).map(Arc::new)
+                                        })
+                                        .await
+                                        .map_err(|e| e.to_string())?
+                                    },
+                                    UIMessage::TranscriberLoaded,
+                                ),
+                            ]);
+                        }
+                        return stop;
                     }
                     Err(e) => {
                         self.input_error = format!("Error when parsing new config {}", e);
@@ -108,35 +403,509 @@ impl PotatoApp {
                 };
                 Task::none()
             }
+            UIMessage::TranscriberLoaded(result) => {
+                self.transcriber = result;
+                Task::none()
+            }
             UIMessage::HandleSettingsInput(action) => {
                 self.user_settings.perform(action);
                 Task::none()
             }
             UIMessage::NewConversation => {
+                self.new_conv_input = "".to_string();
+                self.input_error = "".to_string();
                 self.view = AppView::NewConversation;
                 Task::none()
             }
-            UIMessage::StartAudio => match self.audio_rec.start() {
-                Ok(_) => Task::none(),
-                Err(e) => {
-                    println!("Error during trying to record input: {}", e);
-                    Task::none()
+            UIMessage::NewConversationInputHandle(new_value) => {
+                self.new_conv_input = new_value;
+                Task::none()
+            }
+            UIMessage::SubmitNewConversation => {
+                match self.history.create_conversation(&self.new_conv_input) {
+                    Ok(id) => {
+                        self.active_conversation = id;
+                        self.new_conv_input = "".to_string();
+                        self.input_error = "".to_string();
+                        self.view = AppView::Chat;
+                    }
+                    Err(e) => self.input_error = e,
+                };
+                Task::none()
+            }
+            UIMessage::CancelNewConversation => {
+                self.new_conv_input = "".to_string();
+                self.input_error = "".to_string();
+                self.view = AppView::Chat;
+                Task::none()
+            }
+            UIMessage::SelectConversation(id) => {
+                self.active_conversation = id;
+                self.editing_message = None;
+                Task::none()
+            }
+            UIMessage::StartAudio => {
+                // Push-to-talk sets it again once the recording started
+                self.push_to_talk = false;
+                // The recording takes the microphone over
+                self.wake_listener.stop();
+                self.mic_error = self.audio_rec.start().err();
+                if self.mic_error.is_some() {
+                    self.conversation_mode = false;
                 }
-            },
-            UIMessage::EndAudio => match self.audio_rec.stop() {
-                Ok(_) => {
-                    self.messages.push(AiMessage {
-                        from: AiMessageFrom::User,
-                        content: "Sent vocal".to_string(),
-                    });
-                    Task::none()
-                }
-                Err(e) => {
-                    println!("Error during trying to record input: {}", e);
-                    Task::none()
+                self.input_before_recording = self.user_input.clone();
+                self.live_transcript = LiveTranscript::default();
+                self.next_partial_at = PARTIAL_TRANSCRIPTION_INTERVAL;
+                self.recording_id += 1;
+                self.partial_transcribing = false;
+                Task::none()
+            }
+            UIMessage::CheckRecording => {
+                self.mic_level = self.audio_rec.get_level();
+                self.check_recording()
+            }
+            // Auto-stop and a click may both ask to end the same recording
+            UIMessage::EndAudio if !self.audio_rec.recording => {
+                self.push_to_talk = false;
+                Task::none()
+            }
+            UIMessage::EndAudio => {
+                // A click, a device error or the key release, the next recording stops on silence again
+                self.push_to_talk = false;
+                // Only what the live transcription has not committed yet is left to transcribe
+                match self.audio_rec.stop(self.live_transcript.committed_until) {
+                    Ok(samples) => match self.get_transcription_task(samples) {
+                        Some(task) => {
+                            self.transcribing = true;
+                            Task::perform(task, UIMessage::TranscriptionDone)
+                        }
+                        None => Task::none(),
+                    },
+                    Err(e) => {
+                        println!("Error during trying to record input: {}", e);
+                        // Nothing more was said, the committed text is the whole transcription
+                        Task::done(UIMessage::TranscriptionDone(Ok(Transcript::default())))
+                    }
+                }
+            }
+            UIMessage::PartialTranscriptionDone(result, window, recording_id) => {
+                // Audio of a previous recording, the running one has its own partials
+                if recording_id != self.recording_id {
+                    return Task::none();
+                }
+                self.partial_transcribing = false;
+                // Too late, the final transcription takes over
+                if !self.audio_rec.recording {
+                    return Task::none();
+                }
+                match result {
+                    Ok(transcript) => {
+                        self.live_transcript.update(&transcript, window);
+                        self.user_input =
+                            join_text(&self.input_before_recording, &self.live_transcript.text());
+                    }
+                    Err(e) => println!("Error during live transcription: {}", e),
+                };
+                Task::none()
+            }
+            UIMessage::TranscriptionDone(result) => {
+                self.transcribing = false;
+                match result {
+                    Ok(transcript) => {
+                        self.detected_language = SttOptions::from_config(&self.config)
+                            .is_auto()
+                            .then(|| transcript.language.clone())
+                            .flatten();
+                        // Replaces the partial text shown while recording
+                        let text = self.live_transcript.finish(&transcript);
+                        if text.is_empty() {
+                            println!("Nothing was transcribed");
+                        }
+                        self.user_input = join_text(&self.input_before_recording, &text);
+                    }
+                    Err(e) => {
+                        println!("Error during transcription: {}", e);
+                        self.mic_error = Some(format!("Transcription failed: {}", e));
+                        // Listening again would fail the same way and hide the error
+                        self.conversation_mode = false;
+                    }
+                };
+                self.live_transcript = LiveTranscript::default();
+                if !self.conversation_mode {
+                    return Task::none();
+                }
+                if self.user_input.trim().is_empty() {
+                    // Nothing was said, keep listening
+                    self.update(UIMessage::StartAudio)
+                } else {
+                    self.update(UIMessage::SendMessage)
+                }
+            }
+            UIMessage::SpeakMessage(index) => {
+                let Some(message) = self
+                    .history
+                    .get_messages(&self.active_conversation)
+                    .get(index)
+                else {
+                    return Task::none();
+                };
+                for sentence in SentenceSegmenter::split(&message.content) {
+                    self.speaker.speak(&sentence);
+                }
+                self.speaker_error = None;
+                self.speak_next()
+            }
+            UIMessage::SpeechReady(result, speech_id) => {
+                // Finished just before the user stopped the speaker
+                if speech_id != self.speech_id {
+                    return Task::none();
+                }
+                self.speaker.synthesizing = false;
+                self.speech_task = None;
+                if let Err(e) = result.and_then(|speech| self.speaker.play(speech)) {
+                    println!("Error during speech synthesis: {}", e);
+                    self.speaker_error = Some(e);
+                }
+                self.speak_next()
+            }
+            UIMessage::StopSpeaking => {
+                if let Some(handle) = self.speech_task.take() {
+                    handle.abort();
+                }
+                self.speaker.stop();
+                self.speech_id += 1;
+                self.reply_muted = true;
+                Task::none()
+            }
+            // Only refreshes the view until the speaker is done
+            UIMessage::CheckSpeaking => Task::none(),
+            UIMessage::ToggleConversationMode => {
+                self.conversation_mode = !self.conversation_mode;
+                if !self.conversation_mode {
+                    // What was said so far stays in the input, unsent
+                    return Task::done(UIMessage::EndAudio);
+                }
+                if self.audio_rec.recording || self.transcribing {
+                    return Task::none();
+                }
+                self.update(UIMessage::StartAudio)
+            }
+            UIMessage::KeyPressed(key, modifiers) => {
+                match get_shortcut(&self.config, &key, modifiers) {
+                    // Key repeat sends presses while the key is held
+                    Some(Shortcut::PushToTalk)
+                        if self.view == AppView::Chat
+                            && !self.audio_rec.recording
+                            && !self.transcribing
+                            && self.transcriber.is_ok() =>
+                    {
+                        let task = self.update(UIMessage::StartAudio);
+                        self.push_to_talk = self.audio_rec.recording;
+                        task
+                    }
+                    Some(Shortcut::NewConversation) => self.update(UIMessage::NewConversation),
+                    Some(Shortcut::Settings) => {
+                        self.update(UIMessage::ChangeView(AppView::Settings))
+                    }
+                    Some(Shortcut::StopGeneration) => self.update(UIMessage::StopGeneration),
+                    Some(Shortcut::EditLastMessage)
+                        if self.view == AppView::Chat
+                            && self.user_input.is_empty()
+                            && self.generation.is_none()
+                            && !self.audio_rec.recording =>
+                    {
+                        self.edit_last_message();
+                        Task::none()
+                    }
+                    _ => Task::none(),
+                }
+            }
+            UIMessage::CheckWakeWord => self.check_wake_word(),
+            UIMessage::WakeWordChecked(result) => {
+                self.wake_checking = false;
+                match result {
+                    Ok(transcript)
+                        if self.wake_listener.listening
+                            && contains_wake_phrase(
+                                &transcript.text(),
+                                &self.config.wake_phrase,
+                            ) =>
+                    {
+                        println!("Wake word heard");
+                        self.view = AppView::Chat;
+                        Task::batch([
+                            self.update(UIMessage::StopSpeaking),
+                            self.update(UIMessage::StartAudio),
+                        ])
+                    }
+                    Ok(_) => Task::none(),
+                    Err(e) => {
+                        println!("Error during wake word detection: {}", e);
+                        Task::none()
+                    }
                 }
+            }
+            UIMessage::KeyReleased(key) => {
+                if self.push_to_talk
+                    && KeyBinding::parse(&self.config.key_push_to_talk)
+                        .is_some_and(|binding| binding.matches_key(&key))
+                {
+                    self.push_to_talk = false;
+                    return self.update(UIMessage::EndAudio);
+                }
+                Task::none()
+            }
+        }
+    }
+
+    // Speak the sentences completed by the new part of the answer
+    fn speak_reply(&mut self, delta: &str, finished: bool) -> Task<UIMessage> {
+        if self.reply_muted || (!self.config.tts_auto_speak && !self.conversation_mode) {
+            return Task::none();
+        }
+        let mut sentences = self.reply_sentences.push(delta);
+        if finished {
+            sentences.extend(self.reply_sentences.finish());
+        }
+        for sentence in sentences {
+            self.speaker.speak(&sentence);
+        }
+        self.speak_next()
+    }
+
+    // Synthesize the next queued text, the engine runs a process so keep it off the UI thread
+    fn speak_next(&mut self) -> Task<UIMessage> {
+        let Some(text) = self.speaker.take_next() else {
+            return Task::none();
+        };
+        let engine = match self.speaker.get_engine() {
+            Ok(engine) => engine,
+            Err(e) => {
+                self.speaker.stop();
+                self.speaker_error = Some(e);
+                return Task::none();
+            }
+        };
+        let speech_id = self.speech_id;
+        let (task, handle) = Task::perform(
+            async move {
+                tokio::task::spawn_blocking(move || engine.synthesize(&text))
+                    .await
+                    .map_err(|e| e.to_string())?
             },
-            _ => Task::none(),
+            move |result| UIMessage::SpeechReady(result, speech_id),
+        )
+        .abortable();
+        self.speech_task = Some(handle);
+        task
+    }
+
+    // Whisper blocks for a while, keep it away from the UI thread
+    fn get_transcription_task(
+        &self,
+        samples: Vec<f32>,
+    ) -> Option<impl Future<Output = Result<Transcript, String>> + use<>> {
+        let transcriber = self.transcriber.clone().ok()?;
+        let options = SttOptions::from_config(&self.config);
+        Some(async move {
+            tokio::task::spawn_blocking(move || transcriber.transcribe(&samples, &options))
+                .await
+                .map_err(|e| e.to_string())?
+        })
+    }
+
+    // Transcribe what was said since the last committed text, while still recording
+    fn start_partial_transcription(&mut self) -> Task<UIMessage> {
+        let elapsed = self.audio_rec.get_elapsed();
+        if !self.config.stt_streaming
+            || self.partial_transcribing
+            || elapsed < self.next_partial_at
+            || !self.audio_rec.speech_started()
+        {
+            return Task::none();
+        }
+        let samples = self
+            .audio_rec
+            .get_samples(self.live_transcript.committed_until);
+        let window = Duration::from_secs_f32(samples.len() as f32 / WHISPER_SAMPLE_RATE as f32);
+        let Some(task) = self.get_transcription_task(samples) else {
+            return Task::none();
+        };
+        self.partial_transcribing = true;
+        self.next_partial_at = elapsed + PARTIAL_TRANSCRIPTION_INTERVAL;
+        let recording_id = self.recording_id;
+        Task::perform(task, move |result| {
+            UIMessage::PartialTranscriptionDone(result, window, recording_id)
+        })
+    }
+
+    // Stop the recording when the device failed or the user stopped talking
+    fn check_recording(&mut self) -> Task<UIMessage> {
+        match self.audio_rec.take_stream_error() {
+            // Keep what was heard before the device went away
+            Some(e) => {
+                let task = self.update(UIMessage::EndAudio);
+                self.mic_error = Some(format!("Recording interrupted: {}", e));
+                task
+            }
+            None if self.conversation_mode && self.is_assistant_busy() => self.check_barge_in(),
+            // The user stopped talking, transcribe without waiting for a click
+            None if self
+                .get_silence_limit()
+                .is_some_and(|limit| self.audio_rec.get_trailing_silence() >= limit) =>
+            {
+                Task::done(UIMessage::EndAudio)
+            }
+            None => self.start_partial_transcription(),
+        }
+    }
+
+    // Silence after which the recording stops on its own, None to stop it by hand only
+    fn get_silence_limit(&self) -> Option<Duration> {
+        get_silence_limit(
+            self.config.vad_silence_ms,
+            self.conversation_mode,
+            self.push_to_talk,
+        )
+    }
+
+    fn is_assistant_busy(&self) -> bool {
+        self.generation.is_some() || self.speaker.is_speaking()
+    }
+
+    // The microphone also hears the assistant, only a louder voice interrupts it
+    fn check_barge_in(&mut self) -> Task<UIMessage> {
+        let task = if self.mic_level.rms >= self.config.barge_in_threshold {
+            println!("The user interrupted the assistant");
+            Task::batch([
+                self.update(UIMessage::StopSpeaking),
+                self.update(UIMessage::StopGeneration),
+            ])
+        } else {
+            Task::none()
+        };
+        // What was heard until now is the assistant, the user's turn starts here
+        let recorded = self.audio_rec.restart_detection();
+        self.live_transcript = LiveTranscript::after_barge_in(recorded);
+        // Partials in flight cover the assistant's voice
+        self.recording_id += 1;
+        self.partial_transcribing = false;
+        self.next_partial_at = self.audio_rec.get_elapsed() + PARTIAL_TRANSCRIPTION_INTERVAL;
+        task
+    }
+
+    fn subscription(&self) -> Subscription<UIMessage> {
+        let recording = if self.audio_rec.recording {
+            // Fast enough for a smooth level meter
+            time::every(Duration::from_millis(50)).map(|_| UIMessage::CheckRecording)
+        } else {
+            Subscription::none()
+        };
+        let speaking = if self.speaker.is_speaking() {
+            time::every(Duration::from_millis(250)).map(|_| UIMessage::CheckSpeaking)
+        } else {
+            Subscription::none()
+        };
+        let wake_word = if self.config.wake_word_enabled {
+            time::every(Duration::from_millis(250)).map(|_| UIMessage::CheckWakeWord)
+        } else {
+            Subscription::none()
+        };
+        Subscription::batch([
+            recording,
+            speaking,
+            wake_word,
+            event::listen_with(get_key_message),
+        ])
+    }
+
+    // Listen for the wake phrase whenever the microphone is free, Whisper checks what was said
+    fn check_wake_word(&mut self) -> Task<UIMessage> {
+        if let Some(e) = self.wake_listener.take_stream_error() {
+            self.wake_listener.stop();
+            self.wake_error = Some(e);
+            return Task::none();
+        }
+        // Only listen where the indicator is shown
+        let microphone_free = self.view == AppView::Chat
+            && !self.audio_rec.recording
+            && !self.transcribing
+            && !self.conversation_mode
+            && self.transcriber.is_ok();
+        if !microphone_free {
+            self.wake_listener.stop();
+            return Task::none();
+        }
+        if !self.wake_listener.listening {
+            if self.wake_error.is_none() {
+                self.wake_error = self.wake_listener.start().err();
+            }
+            return Task::none();
+        }
+        if self.wake_checking {
+            return Task::none();
+        }
+        let Some(task) = self
+            .wake_listener
+            .take_utterance()
+            .and_then(|samples| self.get_transcription_task(samples))
+        else {
+            return Task::none();
+        };
+        self.wake_checking = true;
+        Task::perform(task, UIMessage::WakeWordChecked)
+    }
+
+    // Put the last question back in the input, it and its answer are replaced once sent
+    fn edit_last_message(&mut self) {
+        let messages = self.history.get_messages(&self.active_conversation);
+        let Some(index) = messages.iter().rposition(|m| m.from == AiMessageFrom::User) else {
+            return;
+        };
+        self.user_input = messages[index].content.clone();
+        self.editing_message = Some((self.active_conversation, index));
+    }
+
+    // Conversation as sent to the AI, without the errors displayed in the chat
+    fn get_ai_history(&self, conversation: &Uuid) -> Vec<AiMessage> {
+        self.history
+            .get_messages(conversation)
+            .iter()
+            .filter(|m| m.error.is_none())
+            .cloned()
+            .collect()
+    }
+
+    fn push_error(&mut self, conversation: &Uuid, error: AiError) {
+        // Stopping on purpose is not an error worth showing
+        if error == AiError::Cancelled {
+            return;
+        }
+        self.drop_empty_reply(conversation);
+        self.history
+            .push_message(conversation, AiMessage::from_error(error));
+    }
+
+    fn mark_reply_truncated(&mut self, conversation: &Uuid) {
+        if let Some(last) = self
+            .history
+            .get_messages_mut(conversation)
+            .and_then(|messages| messages.last_mut())
+            && last.from == AiMessageFrom::Assistant
+            && last.error.is_none()
+        {
+            last.truncated = true;
+        }
+    }
+
+    // Remove the streaming placeholder when nothing came back
+    fn drop_empty_reply(&mut self, conversation: &Uuid) {
+        if let Some(messages) = self.history.get_messages_mut(conversation)
+            && messages
+                .last()
+                .is_some_and(|m| m.from == AiMessageFrom::Assistant && m.content.is_empty())
+        {
+            messages.pop();
         }
     }
 
@@ -152,8 +921,8 @@ impl PotatoApp {
 }
 
 fn main() -> iced::Result {
-    LocalTranscriber::new("models/ggml-base.en.bin");
     iced::application("Potato Assistant", PotatoApp::update, PotatoApp::view)
         .theme(|_| Theme::Dark)
+        .subscription(PotatoApp::subscription)
         .run()
 }
@@ -1,6 +1,8 @@
 use iced::{
     Alignment, Border, Element, Length,
-    widget::{button, column, container, row, scrollable, text, text_input, vertical_rule},
+    widget::{
+        button, column, container, progress_bar, row, scrollable, text, text_input, vertical_rule,
+    },
 };
 
 use crate::{
@@ -16,7 +18,18 @@ pub fn get_chat_view(_state: &crate::PotatoApp) -> Element<'_, messages::UIMessa
 }
 
 fn get_conversations_view(_state: &crate::PotatoApp) -> Element<'_, messages::UIMessage> {
-    let history = column(_state.history.conversations.keys().map(|k| text(k).into()));
+    let history = column(_state.history.get_conversations().into_iter().map(|c| {
+        let style = if c.id == _state.active_conversation {
+            button::primary
+        } else {
+            button::text
+        };
+        button(text(&c.title))
+            .style(style)
+            .width(Length::Fill)
+            .on_press(UIMessage::SelectConversation(c.id))
+            .into()
+    }));
     container(row![
         column![
             text("Conversations").size(30),
@@ -32,7 +45,15 @@ fn get_conversations_view(_state: &crate::PotatoApp) -> Element<'_, messages::UI
 }
 
 fn get_right_view(state: &crate::PotatoApp) -> Element<'_, messages::UIMessage> {
-    let messages_column = column(state.messages.iter().map(|m| {
+    let conversation = state.history.conversations.get(&state.active_conversation);
+    let title = conversation.map(|c| c.title.as_str()).unwrap_or_default();
+    // Who answered this conversation last, if anyone did
+    let model = match conversation.and_then(|c| c.provider.as_ref().zip(c.model.as_ref())) {
+        Some((provider, model)) => format!("{} ({:?})", model, provider),
+        None => "".to_string(),
+    };
+    let messages = state.history.get_messages(&state.active_conversation);
+    let messages_column = column(messages.iter().enumerate().map(|(index, m)| {
         let mut msg = text(m.content.clone());
         if m.from == AiMessageFrom::User {
             msg = msg.align_x(Alignment::Start).width(Length::Fill);
@@ -40,8 +61,26 @@ fn get_right_view(state: &crate::PotatoApp) -> Element<'_, messages::UIMessage>
             msg = msg.align_x(Alignment::End).width(Length::Fill);
         }
 
-        container(msg)
-            .style(if m.from == AiMessageFrom::User {
+        let content: Element<'_, UIMessage> = if let Some(error) = &m.error {
+            column![text(error.title()).size(12), msg].into()
+        } else if m.from == AiMessageFrom::Assistant {
+            column![msg]
+                .push_maybe(m.truncated.then(|| text("(stopped)").size(12)))
+                .push(
+                    button(text("Play").size(12))
+                        .style(button::text)
+                        .on_press(UIMessage::SpeakMessage(index)),
+                )
+                .align_x(Alignment::End)
+                .into()
+        } else {
+            msg.into()
+        };
+
+        container(content)
+            .style(if m.error.is_some() {
+                get_error_message_container_style
+            } else if m.from == AiMessageFrom::User {
                 get_user_message_container_style
             } else {
                 get_assistant_message_container_style
@@ -56,25 +95,107 @@ fn get_right_view(state: &crate::PotatoApp) -> Element<'_, messages::UIMessage>
     let mut chat_text_input = text_input("Enter your message", state.user_input.as_str());
     let mut send_button = button("Send");
 
+    if state.transcribing {
+        mic_button = button("Transcribing...");
+    } else if state.transcriber.is_err() {
+        mic_button = button("M Start");
+    }
+
     if state.audio_rec.recording {
         mic_button = button("M Stop").on_press(UIMessage::EndAudio);
+    }
+
+    // The transcription fills the input until it is done
+    let input_locked = state.audio_rec.recording || state.transcribing;
+    if !input_locked {
+        chat_text_input = chat_text_input.on_input(UIMessage::UserInputHandle);
+    }
+    let mut conversation_button = button(if state.conversation_mode {
+        "Conversation: on"
+    } else {
+        "Conversation: off"
+    })
+    .style(if state.conversation_mode {
+        button::primary
     } else {
-        chat_text_input = chat_text_input
-            .on_input(UIMessage::UserInputHandle)
-            .on_submit(UIMessage::SendMessage);
+        button::secondary
+    });
+    if state.transcriber.is_ok() {
+        conversation_button = conversation_button.on_press(UIMessage::ToggleConversationMode);
+    }
+
+    let stop_speaking_button = state
+        .speaker
+        .is_speaking()
+        .then(|| button("Stop speaking").on_press(UIMessage::StopSpeaking));
+
+    if state.generation.is_some() {
+        send_button = button("Stop").on_press(UIMessage::StopGeneration);
+    } else if !input_locked {
+        chat_text_input = chat_text_input.on_submit(UIMessage::SendMessage);
         send_button = send_button.on_press(UIMessage::SendMessage)
     }
 
     container(
         column![
             row![
-                text("Chat").size(30).width(Length::Fill),
-                button("Settings").on_press(UIMessage::ChangeView(AppView::Settings))
+                text(title).size(30),
+                text(model).size(12).width(Length::Fill),
             ]
+            .push_maybe(get_wake_word_view(state))
+            .push(button("Settings").on_press(UIMessage::ChangeView(AppView::Settings)))
+            .spacing(10)
             .align_y(Alignment::Center),
             scrollable(messages_column).height(Length::Fill),
-            row![chat_text_input, send_button, mic_button].spacing(10)
+            row![
+                chat_text_input,
+                send_button,
+                mic_button,
+                conversation_button
+            ]
+            .push_maybe(stop_speaking_button)
+            .spacing(10),
         ]
+        .push_maybe(state.conversation_mode.then(|| {
+            text(
+                if state.generation.is_some() || state.speaker.is_speaking() {
+                    "Assistant answering, speak up to interrupt"
+                } else if state.transcribing {
+                    "Transcribing..."
+                } else {
+                    "Listening..."
+                },
+            )
+            .size(12)
+        }))
+        .push_maybe(
+            state
+                .editing_message
+                .is_some()
+                .then(|| text("Editing your last message, sending replaces it").size(12)),
+        )
+        .push_maybe(state.audio_rec.recording.then(|| get_recording_view(state)))
+        .push_maybe(state.mic_error.as_ref().map(|e| {
+            text(format!("Microphone: {}", e))
+                .size(12)
+                .style(text::danger)
+        }))
+        .push_maybe(state.transcriber.as_ref().err().map(|e| {
+            text(format!("Speech to text unavailable: {}", e))
+                .size(12)
+                .style(text::danger)
+        }))
+        .push_maybe(state.speaker_error.as_ref().map(|e| {
+            text(format!("Speech unavailable: {}", e))
+                .size(12)
+                .style(text::danger)
+        }))
+        .push_maybe(
+            state
+                .detected_language
+                .as_ref()
+                .map(|language| text(format!("Detected language: {}", language)).size(12)),
+        )
         .spacing(10)
         .padding(10)
         .height(Length::Fill),
@@ -82,6 +203,34 @@ fn get_right_view(state: &crate::PotatoApp) -> Element<'_, messages::UIMessage>
     .into()
 }
 
+// Always listening must never go unnoticed
+fn get_wake_word_view(state: &crate::PotatoApp) -> Option<Element<'_, messages::UIMessage>> {
+    if !state.config.wake_word_enabled {
+        return None;
+    }
+    let indicator = match &state.wake_error {
+        Some(e) => text(format!("Wake word off: {}", e)).style(text::danger),
+        None if state.wake_listener.listening => {
+            text(format!("Listening for \"{}\"", state.config.wake_phrase)).style(text::primary)
+        }
+        None => text("Wake word paused"),
+    };
+    Some(indicator.size(12).into())
+}
+
+// Level meter and elapsed time of the running recording
+fn get_recording_view(state: &crate::PotatoApp) -> Element<'_, messages::UIMessage> {
+    let elapsed = state.audio_rec.get_elapsed().as_secs();
+    row![
+        text(format!("{:02}:{:02}", elapsed / 60, elapsed % 60)).size(12),
+        progress_bar(0.0..=1.0, state.mic_level.get_meter()).height(8),
+        text(format!("peak {:.0} dB", state.mic_level.get_peak_db())).size(12)
+    ]
+    .spacing(10)
+    .align_y(Alignment::Center)
+    .into()
+}
+
 fn get_user_message_container_style(theme: &iced::Theme) -> iced::widget::container::Style {
     let palette = theme.extended_palette();
     iced::widget::container::Style {
@@ -109,3 +258,17 @@ fn get_assistant_message_container_style(theme: &iced::Theme) -> iced::widget::c
         ..Default::default()
     }
 }
+
+fn get_error_message_container_style(theme: &iced::Theme) -> iced::widget::container::Style {
+    let palette = theme.extended_palette();
+    iced::widget::container::Style {
+        background: Some(palette.danger.weak.color.into()),
+        text_color: Some(palette.danger.weak.text),
+        border: Border {
+            radius: 5.into(),
+            color: palette.danger.strong.color,
+            width: 1.0,
+        },
+        ..Default::default()
+    }
+}
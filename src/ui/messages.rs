@@ -1,18 +1,55 @@
-use iced::widget::text_editor;
+use std::{sync::Arc, time::Duration};
 
-use crate::ui::views::AppView;
+use iced::{
+    keyboard::{Key, Modifiers},
+    widget::text_editor,
+};
+
+use uuid::Uuid;
+
+use crate::{
+    api::error::AiError,
+    audio::{
+        speaker::Speech,
+        stt::{LocalTranscriber, Transcript},
+    },
+    ui::views::AppView,
+};
 
 #[derive(Debug, Clone)]
 pub enum UIMessage {
     None,
     UserInputHandle(String),
     SendMessage,
+    ReceiveAiMessage(Result<String, AiError>),
+    StreamChunkReceived(String),
+    StreamFinished,
+    StreamFailed(AiError),
+    StopGeneration,
     ChangeView(AppView),
     HandleSettingsInput(text_editor::Action),
     SaveSettings,
+    ModelsLoaded(Result<Vec<String>, AiError>),
     NewConversation,
+    NewConversationInputHandle(String),
     SubmitNewConversation,
     CancelNewConversation,
+    SelectConversation(Uuid),
     StartAudio,
     EndAudio,
+    CheckRecording,
+    // Live transcription of the recording, the length of audio it covers and the recording id
+    PartialTranscriptionDone(Result<Transcript, String>, Duration, u64),
+    TranscriptionDone(Result<Transcript, String>),
+    TranscriberLoaded(Result<Arc<LocalTranscriber>, String>),
+    // Index of the message in the active conversation
+    SpeakMessage(usize),
+    SpeechReady(Result<Speech, String>, u64),
+    StopSpeaking,
+    CheckSpeaking,
+    ToggleConversationMode,
+    CheckWakeWord,
+    WakeWordChecked(Result<Transcript, String>),
+    KeyPressed(Key, Modifiers),
+    KeyReleased(Key),
 }
@@ -2,4 +2,5 @@ pub mod chat;
 pub mod messages;
 pub mod new_conversation;
 pub mod settings;
+pub mod shortcuts;
 pub mod views;
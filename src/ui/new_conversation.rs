@@ -8,7 +8,10 @@ use crate::{PotatoApp, ui::messages::UIMessage};
 pub fn get_new_conversation_view(state: &PotatoApp) -> Element<'_, UIMessage> {
     let content = column![
         text("New conversation"),
-        text_input("Enter conversation name", &state.new_conv_input),
+        text_input("Enter conversation name", &state.new_conv_input)
+            .on_input(UIMessage::NewConversationInputHandle)
+            .on_submit(UIMessage::SubmitNewConversation),
+        text(&state.input_error),
         row![
             button("Cancel").on_press(UIMessage::CancelNewConversation),
             button("Create").on_press(UIMessage::SubmitNewConversation)
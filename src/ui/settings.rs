@@ -19,6 +19,33 @@ pub fn get_settings_view(state: &PotatoApp) -> Element<'_, UIMessage> {
         text_editor(&state.user_settings)
             .height(Length::Fill)
             .on_action(UIMessage::HandleSettingsInput),
+        text(if state.available_models.is_empty() {
+            "No model listed by the AI provider".to_string()
+        } else {
+            format!("Available models: {}", state.available_models.join(", "))
+        }),
+        text(if state.input_devices.is_empty() {
+            "No microphone found".to_string()
+        } else {
+            format!("Microphones: {}", state.input_devices.join(", "))
+        }),
+        text(if state.stt_models.is_empty() {
+            "No Whisper model found".to_string()
+        } else {
+            format!(
+                "Whisper models: {}",
+                state
+                    .stt_models
+                    .iter()
+                    .map(|model| model.describe())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        }),
+        text(match &state.transcriber {
+            Ok(_) => "".to_string(),
+            Err(e) => e.clone(),
+        }),
         text(&state.input_error),
         button("Save settings").on_press(UIMessage::SaveSettings)
     ]
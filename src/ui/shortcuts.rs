@@ -0,0 +1,221 @@
+use iced::{
+    Event, event,
+    keyboard::{self, Key, Modifiers, key::Named},
+    window,
+};
+
+use crate::{config::AppConfig, ui::messages::UIMessage};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shortcut {
+    // Record while the key is held, transcribe when it is released
+    PushToTalk,
+    NewConversation,
+    Settings,
+    StopGeneration,
+    EditLastMessage,
+}
+
+// A key and its modifiers as written in the config, e.g. "Ctrl+N" or "F2"
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyBinding {
+    // Ctrl, or Cmd on macOS
+    pub command: bool,
+    pub shift: bool,
+    pub alt: bool,
+    // Lowercase character or named key
+    pub key: Key,
+}
+
+impl KeyBinding {
+    // None for an empty binding, which disables the shortcut, or an unknown key or modifier
+    pub fn parse(binding: &str) -> Option<Self> {
+        let binding = binding.trim();
+        // "Ctrl++" binds the plus key
+        let (modifiers, key) = match binding.strip_suffix("++") {
+            Some(modifiers) => (modifiers, "+"),
+            None => binding.rsplit_once('+').unwrap_or(("", binding)),
+        };
+        if key.is_empty() {
+            return None;
+        }
+
+        let mut key_binding = Self {
+            command: false,
+            shift: false,
+            alt: false,
+            key: match get_named_key(&key.to_lowercase()) {
+                Some(named) => Key::Named(named),
+                None if key.chars().count() == 1 => Key::Character(key.to_lowercase().into()),
+                None => return None,
+            },
+        };
+        for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+            match modifier.trim().to_lowercase().as_str() {
+                "ctrl" | "control" | "cmd" | "command" => key_binding.command = true,
+                "shift" => key_binding.shift = true,
+                "alt" | "option" => key_binding.alt = true,
+                _ => return None,
+            }
+        }
+        Some(key_binding)
+    }
+
+    pub fn matches(&self, key: &Key, modifiers: Modifiers) -> bool {
+        self.matches_key(key)
+            && self.command == modifiers.command()
+            && self.shift == modifiers.shift()
+            && self.alt == modifiers.alt()
+    }
+
+    // Modifiers are ignored, they may be released first
+    pub fn matches_key(&self, key: &Key) -> bool {
+        match (key, &self.key) {
+            (Key::Character(c), Key::Character(bound)) => c.to_lowercase() == bound.as_str(),
+            (Key::Named(named), Key::Named(bound)) => named == bound,
+            _ => false,
+        }
+    }
+}
+
+// Key names accepted in the config, lowercase
+fn get_named_key(name: &str) -> Option<Named> {
+    let named = match name {
+        "escape" | "esc" => Named::Escape,
+        "enter" | "return" => Named::Enter,
+        "space" => Named::Space,
+        "tab" => Named::Tab,
+        "backspace" => Named::Backspace,
+        "delete" | "del" => Named::Delete,
+        "insert" | "ins" => Named::Insert,
+        "home" => Named::Home,
+        "end" => Named::End,
+        "pageup" => Named::PageUp,
+        "pagedown" => Named::PageDown,
+        "arrowup" | "up" => Named::ArrowUp,
+        "arrowdown" | "down" => Named::ArrowDown,
+        "arrowleft" | "left" => Named::ArrowLeft,
+        "arrowright" | "right" => Named::ArrowRight,
+        "f1" => Named::F1,
+        "f2" => Named::F2,
+        "f3" => Named::F3,
+        "f4" => Named::F4,
+        "f5" => Named::F5,
+        "f6" => Named::F6,
+        "f7" => Named::F7,
+        "f8" => Named::F8,
+        "f9" => Named::F9,
+        "f10" => Named::F10,
+        "f11" => Named::F11,
+        "f12" => Named::F12,
+        _ => return None,
+    };
+    Some(named)
+}
+
+pub fn get_shortcut(config: &AppConfig, key: &Key, modifiers: Modifiers) -> Option<Shortcut> {
+    [
+        (Shortcut::PushToTalk, &config.key_push_to_talk),
+        (Shortcut::NewConversation, &config.key_new_conversation),
+        (Shortcut::Settings, &config.key_settings),
+        (Shortcut::StopGeneration, &config.key_stop_generation),
+        (Shortcut::EditLastMessage, &config.key_edit_last_message),
+    ]
+    .into_iter()
+    .find(|(_, binding)| KeyBinding::parse(binding).is_some_and(|b| b.matches(key, modifiers)))
+    .map(|(shortcut, _)| shortcut)
+}
+
+// Keys go through even when a text field has the focus, except the text typed in it
+pub fn get_key_message(
+    event: Event,
+    status: event::Status,
+    _window: window::Id,
+) -> Option<UIMessage> {
+    match event {
+        Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) => {
+            let typed = status == event::Status::Captured
+                && matches!(key, Key::Character(_))
+                && !modifiers.command()
+                && !modifiers.alt();
+            (!typed).then_some(UIMessage::KeyPressed(key, modifiers))
+        }
+        Event::Keyboard(keyboard::Event::KeyReleased { key, .. }) => {
+            Some(UIMessage::KeyReleased(key))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_default_config;
+
+    #[test]
+    fn parses_bindings() {
+        assert_eq!(
+            KeyBinding::parse("Ctrl+Shift+N"),
+            Some(KeyBinding {
+                command: true,
+                shift: true,
+                alt: false,
+                key: Key::Character("n".into())
+            })
+        );
+        assert_eq!(
+            KeyBinding::parse("Ctrl+,").unwrap().key,
+            Key::Character(",".into())
+        );
+        assert_eq!(
+            KeyBinding::parse("Ctrl++").unwrap().key,
+            Key::Character("+".into())
+        );
+        assert_eq!(
+            KeyBinding::parse("Esc").unwrap().key,
+            Key::Named(Named::Escape)
+        );
+        assert_eq!(
+            KeyBinding::parse("Shift+PageDown").unwrap().key,
+            Key::Named(Named::PageDown)
+        );
+        assert_eq!(
+            KeyBinding::parse("F12").unwrap().key,
+            Key::Named(Named::F12)
+        );
+        assert_eq!(KeyBinding::parse("Ctrl+Nope"), None);
+        assert_eq!(KeyBinding::parse(""), None);
+        assert_eq!(KeyBinding::parse("Hyper+N"), None);
+    }
+
+    #[test]
+    fn default_bindings_are_found() {
+        let config = get_default_config();
+
+        assert_eq!(
+            get_shortcut(&config, &Key::Character("n".into()), Modifiers::COMMAND),
+            Some(Shortcut::NewConversation)
+        );
+        assert_eq!(
+            get_shortcut(&config, &Key::Character(",".into()), Modifiers::COMMAND),
+            Some(Shortcut::Settings)
+        );
+        assert_eq!(
+            get_shortcut(&config, &Key::Named(Named::Escape), Modifiers::empty()),
+            Some(Shortcut::StopGeneration)
+        );
+        assert_eq!(
+            get_shortcut(&config, &Key::Named(Named::ArrowUp), Modifiers::empty()),
+            Some(Shortcut::EditLastMessage)
+        );
+        assert_eq!(
+            get_shortcut(&config, &Key::Named(Named::F2), Modifiers::empty()),
+            Some(Shortcut::PushToTalk)
+        );
+        // Without Ctrl, N is only a letter
+        assert_eq!(
+            get_shortcut(&config, &Key::Character("n".into()), Modifiers::empty()),
+            None
+        );
+    }
+}